@@ -0,0 +1,151 @@
+// Encodes frames for APA102 LEDs. Each LED takes a 32 bit word: three set bits and a 5 bit
+// brightness, then the channels in the strip's color order (blue, green, red for most). A frame
+// is a start frame of 32 zero bits, the LEDs in chain order, and an end frame that clocks the
+// data through to the end of the chain.
+use serde::{Serialize, Deserialize};
+
+use crate::{Dither, FloatColor};
+
+pub const MAX_BRIGHTNESS: u8 = 31;
+
+// The order a strip takes its channels in. Clones of the APA102 don't all agree.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorOrder {
+    Rgb,
+    Bgr,
+    Grb,
+}
+
+impl Default for ColorOrder {
+    fn default() -> Self { ColorOrder::Bgr }
+}
+
+// The brightness and channels for one LED.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Apa102Pixel {
+    pub brightness: u8,  // 0-31.
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Apa102Pixel {
+    // Splits a color (0-255 channels) between the brightness field and the channels. The lowest
+    // brightness that fits the brightest channel leaves the channels as much range as possible,
+    // so dim colors keep their hue and their fades stay smooth: about 13 bits all together.
+    pub fn from_color(color: FloatColor) -> Self {
+        let clamp = |value: f32| value.max(0.0).min(255.0);
+        let (r, g, b) = (clamp(color.r), clamp(color.g), clamp(color.b));
+        let brightest = r.max(g).max(b);
+        if brightest <= 0.0 {
+            return Apa102Pixel::default();
+        }
+        let brightness = (brightest * MAX_BRIGHTNESS as f32 / 255.0).ceil().max(1.0).min(MAX_BRIGHTNESS as f32);
+        let channel = |value: f32| (value * MAX_BRIGHTNESS as f32 / brightness).round().min(255.0) as u8;
+        Apa102Pixel { brightness: brightness as u8, r: channel(r), g: channel(g), b: channel(b) }
+    }
+
+    pub fn channels(&self, order: ColorOrder) -> [u8; 3] {
+        match order {
+            ColorOrder::Rgb => [self.r, self.g, self.b],
+            ColorOrder::Bgr => [self.b, self.g, self.r],
+            ColorOrder::Grb => [self.g, self.r, self.b],
+        }
+    }
+
+    // The color this shows, for comparing with what was asked for.
+    pub fn color(&self) -> FloatColor {
+        let scale = self.brightness as f32 / MAX_BRIGHTNESS as f32;
+        FloatColor { r: self.r as f32 * scale, g: self.g as f32 * scale, b: self.b as f32 * scale }
+    }
+
+    // Like from_color, carrying what the steps leave over to the LED's next frame.
+    pub fn dithered(dither: &mut Dither, index: usize, color: FloatColor) -> Self {
+        dither.pixel(index, color, |wanted| {
+            let pixel = Apa102Pixel::from_color(wanted);
+            (pixel, pixel.color())
+        })
+    }
+}
+
+// Bytes of end frame needed for a chain. Each LED delays the clock by half a cycle, so the data
+// needs an extra clock edge for every two LEDs to reach the end.
+pub fn end_frame_length(pixels: usize) -> usize {
+    (pixels + 15) / 16
+}
+
+// Replaces the contents of frame with the SPI bytes for the LEDs, each in its own color order.
+pub fn encode(pixels: &[(Apa102Pixel, ColorOrder)], frame: &mut Vec<u8>) {
+    frame.clear();
+    frame.extend(&[0u8; 4]);
+    for (pixel, order) in pixels.iter() {
+        frame.push(0xE0 | (pixel.brightness & MAX_BRIGHTNESS));
+        frame.extend(&pixel.channels(*order));
+    }
+    // Zeros rather than ones, so an LED past the end of the chain isn't lit white.
+    frame.resize(frame.len() + end_frame_length(pixels.len()), 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn end_frame_has_a_bit_for_every_two_leds() {
+        assert_eq!(end_frame_length(0), 0);
+        assert_eq!(end_frame_length(1), 1);
+        assert_eq!(end_frame_length(16), 1);
+        assert_eq!(end_frame_length(17), 2);
+        assert_eq!(end_frame_length(500), 32);
+    }
+
+    #[test]
+    fn encodes_start_pixels_and_end_frames() {
+        let pixels = [
+            (Apa102Pixel { brightness: 31, r: 1, g: 2, b: 3 }, ColorOrder::Bgr),
+            (Apa102Pixel { brightness: 5, r: 4, g: 5, b: 6 }, ColorOrder::Rgb),
+            (Apa102Pixel { brightness: 0, r: 7, g: 8, b: 9 }, ColorOrder::Grb),
+        ];
+        let mut frame = vec![0xAA; 3];  // Replaced, not added to.
+        encode(&pixels, &mut frame);
+        assert_eq!(frame, vec![0, 0, 0, 0,
+                               0xFF, 3, 2, 1,
+                               0xE5, 4, 5, 6,
+                               0xE0, 8, 7, 9,
+                               0]);
+
+        let pixels = vec![(Apa102Pixel::default(), ColorOrder::Bgr); 17];
+        encode(&pixels, &mut frame);
+        assert_eq!(frame.len(), 4 + 17 * 4 + 2);
+        assert!(frame[4..4 + 17 * 4].chunks(4).all(|led| led == [0xE0, 0, 0, 0]));
+        assert_eq!(&frame[4 + 17 * 4..], &[0, 0]);
+    }
+
+    #[test]
+    fn dim_colors_keep_their_precision() {
+        for step in 0..=100 {
+            let value = step as f32 * 0.1;
+            let color = FloatColor { r: value, g: value / 2.0, b: 0.0 };
+            let shown = Apa102Pixel::from_color(color).color();
+            assert!((shown.r - color.r).abs() <= 1.0 / 31.0, "{} shown as {}", color.r, shown.r);
+            assert!((shown.g - color.g).abs() <= 1.0 / 31.0, "{} shown as {}", color.g, shown.g);
+            assert_eq!(shown.b, 0.0);
+        }
+        let full = Apa102Pixel::from_color(FloatColor { r: 255.0, g: 255.0, b: 255.0 });
+        assert_eq!(full, Apa102Pixel { brightness: 31, r: 255, g: 255, b: 255 });
+    }
+
+    #[test]
+    fn dithering_averages_out_below_the_lowest_step() {
+        let mut dither = Dither::new(1);
+        let color = FloatColor { r: 0.005, g: 0.0, b: 100.3 };
+        let frames = 1000;
+        let mut total = FloatColor::black();
+        for _ in 0..frames {
+            total = total + Apa102Pixel::dithered(&mut dither, 0, color).color();
+        }
+        assert!((total.r / frames as f32 - color.r).abs() < 0.001);
+        assert!((total.b / frames as f32 - color.b).abs() < 0.01);
+    }
+}
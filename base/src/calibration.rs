@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+
+fn default_gamma() -> f32 { 2.2 }
+fn unity_gains() -> [f32; 3] { [1.0, 1.0, 1.0] }
+
+// How the colors painted are turned into LED values.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    // LED values follow the painted value to this power, so fades look even. 1.0 sends colors as
+    // painted.
+    #[serde(default = "default_gamma")]
+    pub gamma: f32,
+    // Red, green and blue scales, for white balance.
+    #[serde(default = "unity_gains")]
+    pub gains: [f32; 3],
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration { gamma: default_gamma(), gains: unity_gains() }
+    }
+}
+
+impl Calibration {
+    // The LED value for a painted value of channel 0, 1 or 2 (red, green or blue). Both are on
+    // the 0-255 scale.
+    pub fn correct(&self, channel: usize, value: f32) -> f32 {
+        255.0 * self.gains[channel] * (value.max(0.0) / 255.0).powf(self.gamma)
+    }
+}
+
+// Replacements for some of the Calibration fields. Unset fields are left alone.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gamma: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gains: Option<[f32; 3]>,
+}
+
+impl CalibrationOverrides {
+    pub fn apply(&self, calibration: &mut Calibration) {
+        if let Some(gamma) = self.gamma {
+            calibration.gamma = gamma;
+        }
+        if let Some(gains) = self.gains {
+            calibration.gains = gains;
+        }
+    }
+}
+
+// Correction for every LED, plus changes for segments whose strips don't match the rest.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ColorCorrection {
+    #[serde(flatten)]
+    pub calibration: Calibration,
+    // By segment name.
+    #[serde(default)]
+    pub segments: BTreeMap<String, CalibrationOverrides>,
+}
+
+impl ColorCorrection {
+    // Leaves the colors as painted.
+    pub fn identity() -> Self {
+        ColorCorrection { calibration: Calibration { gamma: 1.0, gains: unity_gains() },
+                          segments: BTreeMap::new() }
+    }
+
+    pub fn for_segment(&self, name: &str) -> Calibration {
+        let mut calibration = self.calibration;
+        if let Some(overrides) = self.segments.get(name) {
+            overrides.apply(&mut calibration);
+        }
+        calibration
+    }
+}
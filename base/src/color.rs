@@ -2,7 +2,7 @@ use std::ops;
 use serde::{Serialize, Deserialize};
 
 // A single RGB color.
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -15,7 +15,7 @@ impl Color {
         let r = (hex_code >> 16) as u8;
         let g = (hex_code >> 8) as u8;
         let b = hex_code as u8;
-        Color { r: log_scale(r), g: log_scale(g), b: log_scale(b) }
+        Color { r: r, g: g, b: b }
     }
 
     pub fn black() -> Color {
@@ -27,6 +27,15 @@ impl Color {
     }
 }
 
+impl ops::Add for Color {
+    type Output = Color;
+    fn add(self, rhs: Color) -> Self::Output {
+        return Color {r: self.r.saturating_add(rhs.r),
+                      g: self.g.saturating_add(rhs.g),
+                      b: self.b.saturating_add(rhs.b)};
+    }
+}
+
 impl ops::Mul<f32> for Color {
     type Output = Color;
     fn mul(self, rhs: f32) -> Self::Output {
@@ -44,9 +53,58 @@ impl ops::MulAssign<f32> for Color {
     }
 }
 
-fn log_scale(lin_scale: u8) -> u8 {
-    //let exponent_factor = 255.0_f64.log2() / 255.0;
-    //let val = 2.0_f64.powf(lin_scale as f64 * exponent_factor) - 1.0;
-    //return val as u8;
-    return lin_scale;
+// An RGB color with fractional channels, still on the 0-255 scale, so painters can fade and
+// blend without losing the low bits. Values are only rounded to a Color for the LEDs.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FloatColor {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl FloatColor {
+    pub fn black() -> FloatColor {
+        FloatColor { r: 0.0, g: 0.0, b: 0.0 }
+    }
+
+    // The nearest Color, clamping channels outside 0-255.
+    pub fn round(self) -> Color {
+        let channel = |value: f32| value.round().max(0.0).min(255.0) as u8;
+        Color { r: channel(self.r), g: channel(self.g), b: channel(self.b) }
+    }
+}
+
+impl From<Color> for FloatColor {
+    fn from(color: Color) -> Self {
+        FloatColor { r: color.r as f32, g: color.g as f32, b: color.b as f32 }
+    }
+}
+
+impl ops::Add for FloatColor {
+    type Output = FloatColor;
+    fn add(self, rhs: FloatColor) -> Self::Output {
+        return FloatColor {r: self.r + rhs.r, g: self.g + rhs.g, b: self.b + rhs.b};
+    }
+}
+
+impl ops::Sub for FloatColor {
+    type Output = FloatColor;
+    fn sub(self, rhs: FloatColor) -> Self::Output {
+        return FloatColor {r: self.r - rhs.r, g: self.g - rhs.g, b: self.b - rhs.b};
+    }
+}
+
+impl ops::Mul<f32> for FloatColor {
+    type Output = FloatColor;
+    fn mul(self, rhs: f32) -> Self::Output {
+        return FloatColor {r: self.r * rhs, g: self.g * rhs, b: self.b * rhs};
+    }
+}
+
+impl ops::MulAssign<f32> for FloatColor {
+    fn mul_assign(&mut self, rhs: f32) {
+        self.r *= rhs;
+        self.g *= rhs;
+        self.b *= rhs;
+    }
 }
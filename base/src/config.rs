@@ -0,0 +1,278 @@
+use std::error::Error;
+use serde::{Serialize, Deserialize};
+use serde_json;
+use std::fs::File;
+use std::io::prelude::*;
+
+use crate::calibration::ColorCorrection;
+use crate::power::PowerConfig;
+
+fn default_e131_universe() -> u16 { 1 }
+fn default_spi_clock() -> u32 { 8_000_000 }
+
+// The kinds of output for the frames.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DisplayKind {
+    Null,
+    Blinkt,
+    // APA102s on /dev/spidev<bus>.<chip_select>, or on device if it is set.
+    Spi {
+        #[serde(default)]
+        bus: u8,
+        #[serde(default)]
+        chip_select: u8,
+        #[serde(default = "default_spi_clock")]
+        clock_hz: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<String>,
+    },
+    Emulator,
+    Terminal,
+    Opc {
+        address: String,  // host:port
+        #[serde(default)]
+        channel: u8,
+    },
+    E131 {
+        destination: String,  // "multicast" or host[:port]
+        #[serde(default = "default_e131_universe")]
+        start_universe: u16,
+    },
+    ArtNet {
+        destination: String,  // "multicast" (broadcast for Art-Net) or host[:port]
+        #[serde(default)]
+        start_universe: u16,
+    },
+    Record {
+        path: String,
+    },
+}
+
+// Sends `count` pixels of the frame, from `start`, to a display starting at its pixel `offset`.
+// Frames hold the suit's LEDs followed by the belt's.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Route {
+    pub start: usize,
+    pub count: usize,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+impl Route {
+    // Parses start:count[:offset].
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let numbers = spec.split(':').map(|number| number.parse()).collect::<Result<Vec<usize>, _>>()?;
+        match numbers.as_slice() {
+            [start, count] => Ok(Route { start: *start, count: *count, offset: 0 }),
+            [start, count, offset] => Ok(Route { start: *start, count: *count, offset: *offset }),
+            _ => Err(format!("Expected start:count[:offset] but got {}", spec).into()),
+        }
+    }
+}
+
+// One output for the frames. Several can be shown at once.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DisplayConfig {
+    #[serde(flatten)]
+    pub kind: DisplayKind,
+    // Which pixels the display shows. Empty means the whole frame, except on the LED chain,
+    // where the suit and the belt both start at the first LED because they are swapped over.
+    #[serde(default)]
+    pub routes: Vec<Route>,
+    // Segments to show instead of routes, in order along the display. The belt's segments start
+    // again from the first LED, like on the LED chain.
+    #[serde(default)]
+    pub segments: Vec<String>,
+    // Whether the display gets the gamma and white balance from Config::color. Unset means only
+    // the LED chains do; other displays and their software (like fcserver) apply their own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correct: Option<bool>,
+}
+
+// Splits "address/number" into the address and the number, if there is one.
+fn split_number<T: std::str::FromStr>(spec: &str) -> Result<(String, Option<T>), Box<dyn Error>>
+where T::Err: Error + 'static {
+    let mut parts = spec.splitn(2, '/');
+    let address = parts.next().unwrap().to_string();
+    match parts.next() {
+        Some(number) => Ok((address, Some(number.parse()?))),
+        None => Ok((address, None)),
+    }
+}
+
+impl DisplayKind {
+    // Whether this drives the garment's LEDs, which run off the battery. Null stands in for them
+    // where there aren't any.
+    pub fn is_led_chain(&self) -> bool {
+        match self {
+            DisplayKind::Null | DisplayKind::Blinkt | DisplayKind::Spi { .. } => true,
+            _ => false,
+        }
+    }
+
+    fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let mut parts = spec.splitn(2, ':');
+        let kind = parts.next().unwrap();
+        let rest = parts.next().unwrap_or("");
+        match kind {
+            "null" => Ok(DisplayKind::Null),
+            "blinkt" => Ok(DisplayKind::Blinkt),
+            "spi" if rest.starts_with('/') => {
+                Ok(DisplayKind::Spi { bus: 0, chip_select: 0, clock_hz: default_spi_clock(),
+                                      device: Some(rest.to_string()) })
+            },
+            "spi" => {
+                let (device, clock) = split_number(rest)?;
+                let (bus, chip_select) = match device.as_str() {
+                    "" => (0, 0),
+                    device => {
+                        let mut parts = device.splitn(2, '.');
+                        (parts.next().unwrap().parse()?, parts.next().unwrap_or("0").parse()?)
+                    },
+                };
+                Ok(DisplayKind::Spi { bus: bus, chip_select: chip_select,
+                                      clock_hz: clock.unwrap_or(default_spi_clock()), device: None })
+            },
+            "emulator" => Ok(DisplayKind::Emulator),
+            "terminal" => Ok(DisplayKind::Terminal),
+            "opc" => {
+                let (address, channel) = split_number(rest)?;
+                Ok(DisplayKind::Opc { address: address, channel: channel.unwrap_or(0) })
+            },
+            "e131" => {
+                let (destination, universe) = split_number(rest)?;
+                Ok(DisplayKind::E131 { destination: destination,
+                                       start_universe: universe.unwrap_or(default_e131_universe()) })
+            },
+            "artnet" => {
+                let (destination, universe) = split_number(rest)?;
+                Ok(DisplayKind::ArtNet { destination: destination, start_universe: universe.unwrap_or(0) })
+            },
+            "record" => Ok(DisplayKind::Record { path: rest.to_string() }),
+            _ => Err(format!("Unknown display: {}", spec).into()),
+        }
+    }
+}
+
+impl DisplayConfig {
+    pub fn new(kind: DisplayKind) -> Self {
+        DisplayConfig { kind: kind, routes: Vec::new(), segments: Vec::new(), correct: None }
+    }
+
+    pub fn corrected(&self) -> bool { self.correct.unwrap_or(self.kind.is_led_chain()) }
+
+    // Parses a --display flag: null, blinkt, spi[:bus.chip select][/clock Hz], spi:/device path,
+    // emulator, terminal, opc:host:port[/channel], e131:destination[/start universe],
+    // artnet:destination[/start universe] or record:path, followed by any number of
+    // @start:count[:offset] routes or @segment names.
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let mut parts = spec.split('@');
+        let mut config = DisplayConfig::new(DisplayKind::parse(parts.next().unwrap())?);
+        for part in parts {
+            if part.starts_with(|c: char| c.is_ascii_digit()) {
+                config.routes.push(Route::parse(part)?);
+            } else {
+                config.segments.push(part.to_string());
+            }
+        }
+        if config.routes.len() > 0 && config.segments.len() > 0 {
+            return Err(format!("Give routes or segments for a display, not both: {}", spec).into());
+        }
+        Ok(config)
+    }
+}
+
+// What drives the frame loop.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunnerKind {
+    Timer,     // A timer thread, for running headless.
+    Emulator,  // The GTK main loop, which the emulator display needs.
+}
+
+// How wavesuit is set up on this machine, from config.json and command line flags.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    // Empty means the default for the build: the emulator, blinkt, or nothing.
+    #[serde(default)]
+    pub displays: Vec<DisplayConfig>,
+    // Unset means the emulator's runner if there is an emulator display, otherwise the timer.
+    #[serde(default)]
+    pub runner: Option<RunnerKind>,
+    // Audio input for the painters, e.g. "alsa:default" or "wav:song.wav".
+    #[serde(default)]
+    pub audio: Option<String>,
+    // File to record every shown frame to. The same as adding a record display.
+    #[serde(default)]
+    pub record: Option<String>,
+    // Recording to play back instead of running the painters.
+    #[serde(default)]
+    pub replay: Option<String>,
+    #[serde(default)]
+    pub power: PowerConfig,
+    // Gamma and white balance for the LEDs, applied as frames are shown to the displays that
+    // want it. See DisplayConfig::correct.
+    #[serde(default)]
+    pub color: ColorCorrection,
+}
+
+impl Config {
+    pub fn deserialize(string: &str) -> Result<Self, Box<dyn Error>> {
+        let c: Config = serde_json::from_str(string)?;
+        return Ok(c);
+    }
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut file = File::open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        return Self::deserialize(&contents);
+    }
+
+    // Loads the config file, from --config or config.json if there is one, and applies the other
+    // flags on top of it. --display can be repeated; any given replace the file's displays.
+    pub fn from_args(args: &[String]) -> Result<Self, Box<dyn Error>> {
+        let mut flags = Config::default();
+        let mut path = None;
+        let mut gamma = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+            match arg.as_str() {
+                "--config" => path = Some(value()?.clone()),
+                "--display" => flags.displays.push(DisplayConfig::parse(value()?)?),
+                "--runner" => flags.runner = Some(match value()?.as_str() {
+                    "timer" => RunnerKind::Timer,
+                    "emulator" => RunnerKind::Emulator,
+                    other => return Err(format!("Unknown runner: {}", other).into()),
+                }),
+                "--audio" => flags.audio = Some(value()?.clone()),
+                "--record" => flags.record = Some(value()?.clone()),
+                "--replay" => flags.replay = Some(value()?.clone()),
+                "--power-budget" => flags.power.budget_ma = Some(value()?.parse()?),
+                "--gamma" => gamma = Some(value()?.parse()?),
+                _ => return Err(format!("Unknown option {}", arg).into()),
+            }
+        }
+        let mut config = match path {
+            Some(path) => Config::load(&path)?,
+            None if std::path::Path::new("config.json").exists() => Config::load("config.json")?,
+            None => Config::default(),
+        };
+        config.merge(flags);
+        if let Some(gamma) = gamma {
+            config.color.calibration.gamma = gamma;
+        }
+        Ok(config)
+    }
+
+    // Takes any settings made in other.
+    fn merge(&mut self, other: Config) {
+        if other.displays.len() > 0 { self.displays = other.displays; }
+        if other.runner.is_some() { self.runner = other.runner; }
+        if other.audio.is_some() { self.audio = other.audio; }
+        if other.record.is_some() { self.record = other.record; }
+        if other.replay.is_some() { self.replay = other.replay; }
+        if other.power.budget_ma.is_some() { self.power.budget_ma = other.power.budget_ma; }
+    }
+}
@@ -0,0 +1,38 @@
+// Temporal dithering: each LED remembers how far its last value was from the color asked for and
+// adds that to its next frame, so over a few frames it averages out to the exact color. Slow fades
+// at low brightness glide instead of stepping, and dim colors that would round to black still
+// glow. Used for 8 bit displays and for the APA102's brightness and channel steps alike.
+use crate::FloatColor;
+
+pub struct Dither {
+    errors: Vec<FloatColor>,  // What each LED's last value was off by.
+}
+
+impl Dither {
+    pub fn new(pixels: usize) -> Self {
+        Dither { errors: vec![FloatColor::black(); pixels] }
+    }
+
+    // What to show for the LED at index this frame. quantize turns a color into what the display
+    // takes, and the color that will actually show. LEDs past the end aren't dithered.
+    pub fn pixel<T, F>(&mut self, index: usize, color: FloatColor, quantize: F) -> T
+    where F: Fn(FloatColor) -> (T, FloatColor) {
+        let error = match self.errors.get_mut(index) {
+            Some(error) => error,
+            None => return quantize(color).0,
+        };
+        // Out of range values can't be shown, so clamp them rather than carry the difference.
+        let clamp = |value: f32| value.max(0.0).min(255.0);
+        let wanted = FloatColor { r: clamp(color.r), g: clamp(color.g), b: clamp(color.b) } + *error;
+        let (pixel, shown) = quantize(wanted);
+        *error = wanted - shown;
+        pixel
+    }
+
+    // Forgets the LED's error, when it is set to an exact value.
+    pub fn reset(&mut self, index: usize) {
+        if let Some(error) = self.errors.get_mut(index) {
+            *error = FloatColor::black();
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use serde::{Serialize, Deserialize};
+
+// Counts from the render and output threads, served at /frames.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FrameStatus {
+    pub rendered: u64,
+    pub shown: u64,
+    pub dropped: u64,  // Rendered, then replaced by a newer frame before the output got to it.
+    pub missed: u64,   // Took longer than a tick to render, or to show.
+    pub errors: u64,   // Failed to show on at least one display.
+}
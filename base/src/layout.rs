@@ -0,0 +1,187 @@
+use std::error::Error;
+use serde::{Serialize, Deserialize};
+use serde_json;
+use std::fs::File;
+use std::io::prelude::*;
+
+use crate::apa102::ColorOrder;
+
+// Direction the first strip of a segment is wired in. Strips after that alternate (serpentine).
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Down,
+    Up,
+}
+
+impl Default for Direction {
+    fn default() -> Self { Direction::Down }
+}
+
+// A run of adjacent strips sharing an origin. Segments can be split into several panels when
+// there is a physical gap between strips, like the seam down the back.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Panel {
+    pub strips: usize,
+    pub origin: [f64; 2],  // Position of the first LED of the first strip, in layout units.
+}
+
+// A grid of LED strips driven by one painter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Segment {
+    pub name: String,
+    pub region: String,  // The part of the body the segment covers, e.g. "back" or "arm".
+    pub width: usize,   // Number of strips.
+    pub height: usize,  // LEDs per strip.
+    #[serde(default)]
+    pub belt: bool,
+    #[serde(default)]
+    pub first_strip: Direction,
+    // How far odd strips are shifted along the strip, in LEDs. The painters assume 0.5, which
+    // makes the hexagonal grid.
+    #[serde(default)]
+    pub stagger: f64,
+    pub strip_step: [f64; 2],   // Distance between LEDs on a strip, in layout units.
+    pub column_step: [f64; 2],  // Distance between strips, in layout units.
+    pub panels: Vec<Panel>,
+    // Broken (or deliberately unused) LEDs. They keep their place in the chain but are never
+    // lit. Indices count along the wiring from the start of the segment.
+    #[serde(default)]
+    pub dead: Vec<usize>,
+    // LEDs that were cut out of the chain, so the LEDs after them move up one place. Indices
+    // count along the wiring as the segment was originally built.
+    #[serde(default)]
+    pub spliced: Vec<usize>,
+    // The order the strips take their channels in over SPI.
+    #[serde(default)]
+    pub color_order: ColorOrder,
+}
+
+impl Segment {
+    pub fn size(&self) -> usize { self.width * self.height }
+
+    // Converts a painter index (see painter::get_index) to the LED's position along the chain.
+    // This mapping is its own inverse.
+    pub fn wire_index(&self, index: usize) -> usize {
+        if self.first_strip == Direction::Down {
+            return index;
+        }
+        let strip = index / self.height;
+        strip * self.height + self.height - (index % self.height) - 1
+    }
+
+    // Painter indices of the LEDs still in the chain, in wiring order. Includes dead LEDs.
+    pub fn chain(&self) -> Vec<usize> {
+        (0..self.size())
+            .filter(|wire| !self.spliced.contains(wire))
+            .map(|wire| self.wire_index(wire))
+            .collect()
+    }
+
+    // Position of the LED at painter index, in layout units.
+    pub fn position(&self, index: usize) -> (f64, f64) {
+        let x = index / self.height;
+        let y = if x % 2 == 0 { index % self.height } else { self.height - (index % self.height) - 1 };
+        let along = y as f64 + if x % 2 == 0 { 0.0 } else { self.stagger };
+
+        let mut first_strip = 0;
+        for panel in self.panels.iter() {
+            if x < first_strip + panel.strips {
+                let across = (x - first_strip) as f64;
+                return (panel.origin[0] + across * self.column_step[0] + along * self.strip_step[0],
+                        panel.origin[1] + across * self.column_step[1] + along * self.strip_step[1]);
+            }
+            first_strip += panel.strips;
+        }
+        panic!("Strip {} is outside of segment {}", x, self.name);
+    }
+}
+
+// Describes how the LEDs are arranged on the garments. Segments are listed in wiring order.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Layout {
+    pub scale: f64,  // Layout units across the emulator window.
+    pub segments: Vec<Segment>,
+}
+
+impl Layout {
+    pub fn deserialize(string: &str) -> Result<Self, Box<dyn Error>> {
+        let mut layout: Layout = serde_json::from_str(string)?;
+        for segment in layout.segments.iter_mut() {
+            let strips: usize = segment.panels.iter().map(|panel| panel.strips).sum();
+            if strips != segment.width {
+                return Err(format!("Segment {} has {} strips in its panels but a width of {}",
+                                   segment.name, strips, segment.width).into());
+            }
+            if let Some(led) = segment.dead.iter().chain(segment.spliced.iter())
+                .find(|&&led| led >= segment.size()) {
+                return Err(format!("Segment {} has no LED {}", segment.name, led).into());
+            }
+            // Duplicates would throw off anything counting the spliced LEDs.
+            segment.spliced.sort();
+            segment.spliced.dedup();
+        }
+        return Ok(layout);
+    }
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut file = File::open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        return Self::deserialize(&contents);
+    }
+    // Position of the LED at painter index, scaled so the emulator window spans 0.0 to 1.0.
+    pub fn normalized_position(&self, segment: &Segment, index: usize) -> (f64, f64) {
+        let (x, y) = segment.position(index);
+        (x / self.scale, y / self.scale)
+    }
+    // The segments lit in belt_only mode or otherwise.
+    pub fn active_segments(&self, belt_only: bool) -> Vec<Segment> {
+        self.segments.iter().filter(|segment| segment.belt == belt_only).cloned().collect()
+    }
+    // The segment of every display LED: the suit's chain, then the belt's.
+    pub fn display_segments(&self) -> Vec<&Segment> {
+        let mut segments = Vec::new();
+        for belt in [false, true].iter() {
+            for segment in self.segments.iter().filter(|segment| segment.belt == *belt) {
+                segments.resize(segments.len() + segment.chain().len(), segment);
+            }
+        }
+        segments
+    }
+    // Normalized position of every display LED: the suit's chain, then the belt's. Displays that
+    // draw both garments put the belt after an offset of the suit's LEDs.
+    pub fn display_positions(&self) -> Vec<(f64, f64)> {
+        let mut positions = Vec::new();
+        for belt in [false, true].iter() {
+            for segment in self.active_segments(*belt).iter() {
+                for pix in segment.chain() {
+                    positions.push(self.normalized_position(segment, pix));
+                }
+            }
+        }
+        positions
+    }
+}
+
+// Maps each painter index in each segment onto a display index, or None if the LED is dead or
+// spliced out. Display indices count along the chain through all of the segments in order.
+pub fn display_map(segments: &[Segment]) -> Vec<Vec<Option<usize>>> {
+    let mut led: usize = 0;
+    let mut maps = Vec::with_capacity(segments.len());
+    for segment in segments.iter() {
+        let mut map = vec![None; segment.size()];
+        for pix in segment.chain() {
+            if !segment.dead.contains(&segment.wire_index(pix)) {
+                map[pix] = Some(led);
+            }
+            led += 1;
+        }
+        maps.push(map);
+    }
+    maps
+}
+
+// Number of display LEDs needed to drive the segments.
+pub fn chain_length(segments: &[Segment]) -> usize {
+    segments.iter().map(|segment| segment.chain().len()).sum()
+}
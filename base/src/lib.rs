@@ -2,18 +2,66 @@
 #[macro_use] extern crate rocket;
 use rocket::{State, Data};
 use rocket::response::content;
-use std::sync::{Mutex};
+use std::sync::{Arc, Mutex};
 use crossbeam_channel::{bounded, Receiver, Sender};
 use std::{error::Error, thread};
 use std::io::Read;
 
+pub mod apa102;
+mod calibration;
 mod color;
+mod config;
+mod dither;
+mod frames;
+mod layout;
+mod painter_info;
 mod painter_params;
+mod playlist;
+mod power;
 
-pub use color::Color;
-pub use painter_params::PainterParams;
+pub use calibration::{Calibration, CalibrationOverrides, ColorCorrection};
+pub use color::{Color, FloatColor};
+pub use config::{Config, DisplayConfig, DisplayKind, Route, RunnerKind};
+pub use dither::Dither;
+pub use frames::FrameStatus;
+pub use layout::{chain_length, display_map, Direction, Layout, Panel, Segment};
+pub use painter_info::PainterInfo;
+pub use painter_params::{BlendMode, Layer, ParamOverrides, PainterParams, SequenceParams, Zone};
+pub use painter_params::{TransitionKind, TransitionParams, WipeDirection};
+pub use playlist::{Playlist, PlaylistEntry};
+pub use power::{PowerConfig, PowerStatus};
 
-const LIMIT: u64 = 1024;
+// Tries out new params before they are saved, by building their painters. Catches what the
+// painter ids alone can't show, like a sequence file that is missing or corrupt.
+pub type ParamsCheck = Box<dyn Fn(&PainterParams) -> Result<(), Box<dyn Error>> + Send + Sync>;
+
+// Largest request body. Params with layers and zones, and playlists of presets, outgrow 1KB.
+const LIMIT: u64 = 16 * 1024;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PlaylistCommand {
+    Start,
+    Stop,
+    Next,
+    Previous,
+}
+
+// Transport controls for the fseq painter.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SequenceCommand {
+    Play,
+    Pause,
+    Seek(f32),  // Seconds from the start of the sequence.
+}
+
+// Changes requested through the API. Params are sent without dimming applied.
+#[derive(Clone, Debug)]
+pub enum Command {
+    Params(PainterParams),
+    SetPlaylist(Playlist),
+    Playlist(PlaylistCommand),
+    Sequence(SequenceCommand),
+}
 
 #[get("/")]
 fn get(params: State<Mutex<PainterParams>>) -> content::Json<String> {
@@ -21,33 +69,216 @@ fn get(params: State<Mutex<PainterParams>>) -> content::Json<String> {
     content::Json(data.serialize())
 }
 
+#[get("/painters")]
+fn get_painters(painters: State<Vec<PainterInfo>>) -> content::Json<String> {
+    content::Json(serde_json::to_string(&*painters).unwrap())
+}
+
+fn check_painters(params: &PainterParams, painters: &Vec<PainterInfo>, check: &ParamsCheck)
+                  -> Result<(), Box<dyn Error>> {
+    for id in params.painter_ids() {
+        if !painters.iter().any(|info| info.id == id) {
+            return Err(format!("Unknown painter: {}", id).into());
+        }
+    }
+    check(params)
+}
+
+// Checks, saves and sends new params to the painters.
+fn publish(new_params: PainterParams,
+           params: &mut PainterParams,
+           painters: &Vec<PainterInfo>,
+           check: &ParamsCheck,
+           sender: &Sender<Command>) -> Result<(), Box<dyn Error>> {
+    check_painters(&new_params, painters, check)?;
+    *params = new_params.clone();
+    match new_params.save() {
+        Err(e) => println!("Error writing to file: {}", e),
+        Ok(()) => {}
+    }
+    sender.send(Command::Params(new_params)).unwrap();
+    Ok(())
+}
+
+fn read_body(data: Data) -> Result<String, Box<dyn Error>> {
+    let mut body = String::new();
+    if let Err(e) = data.open().take(LIMIT).read_to_string(&mut body) {
+        return Err(Box::new(e));
+    }
+    Ok(body)
+}
+
 #[post("/", format = "application/json", data = "<data>")]
 fn post(data: Data,
         params: State<Mutex<PainterParams>>,
-        sender: State<Sender<PainterParams>>) -> Result<(), Box<dyn Error>> {
-    let mut json_params = String::new();
-    if let Err(e) = data.open().take(LIMIT).read_to_string(&mut json_params) {
-        return Err(Box::new(e));
+        painters: State<Vec<PainterInfo>>,
+        check: State<ParamsCheck>,
+        sender: State<Sender<Command>>) -> Result<(), Box<dyn Error>> {
+    let new_params = PainterParams::deserialize(&read_body(data)?)?;
+    let mut old_params = params.lock().unwrap();
+    publish(new_params, &mut old_params, &painters, &check, &sender)
+}
+
+#[get("/zones")]
+fn get_zones(params: State<Mutex<PainterParams>>) -> content::Json<String> {
+    let data = params.lock().unwrap();
+    content::Json(serde_json::to_string(&data.zones).unwrap())
+}
+
+#[get("/zones/<name>")]
+fn get_zone(name: String, params: State<Mutex<PainterParams>>) -> Option<content::Json<String>> {
+    let data = params.lock().unwrap();
+    data.zones.get(&name).map(|zone| content::Json(serde_json::to_string(zone).unwrap()))
+}
+
+#[post("/zones/<name>", format = "application/json", data = "<data>")]
+fn post_zone(name: String,
+             data: Data,
+             params: State<Mutex<PainterParams>>,
+             painters: State<Vec<PainterInfo>>,
+             check: State<ParamsCheck>,
+             zones: State<Vec<String>>,
+             sender: State<Sender<Command>>) -> Result<(), Box<dyn Error>> {
+    if !zones.contains(&name) {
+        return Err(format!("Unknown zone: {}", name).into());
     }
-    let mut new_params = PainterParams::deserialize(&json_params)?;
+    let zone = Zone::deserialize(&read_body(data)?)?;
     let mut old_params = params.lock().unwrap();
-    *old_params = new_params.clone();
-    match new_params.save() {
+    let mut new_params = old_params.clone();
+    new_params.zones.insert(name, zone);
+    publish(new_params, &mut old_params, &painters, &check, &sender)
+}
+
+#[delete("/zones/<name>")]
+fn delete_zone(name: String,
+               params: State<Mutex<PainterParams>>,
+               painters: State<Vec<PainterInfo>>,
+               check: State<ParamsCheck>,
+               sender: State<Sender<Command>>) -> Result<(), Box<dyn Error>> {
+    let mut old_params = params.lock().unwrap();
+    let mut new_params = old_params.clone();
+    new_params.zones.remove(&name);
+    publish(new_params, &mut old_params, &painters, &check, &sender)
+}
+
+#[get("/transition")]
+fn get_transition(params: State<Mutex<PainterParams>>) -> content::Json<String> {
+    let data = params.lock().unwrap();
+    content::Json(serde_json::to_string(&data.transition).unwrap())
+}
+
+#[post("/transition", format = "application/json", data = "<data>")]
+fn post_transition(data: Data,
+                   params: State<Mutex<PainterParams>>,
+                   painters: State<Vec<PainterInfo>>,
+                   check: State<ParamsCheck>,
+                   sender: State<Sender<Command>>) -> Result<(), Box<dyn Error>> {
+    let transition = TransitionParams::deserialize(&read_body(data)?)?;
+    let mut old_params = params.lock().unwrap();
+    let mut new_params = old_params.clone();
+    new_params.transition = transition;
+    publish(new_params, &mut old_params, &painters, &check, &sender)
+}
+
+#[get("/playlist")]
+fn get_playlist(playlist: State<Mutex<Playlist>>) -> content::Json<String> {
+    let data = playlist.lock().unwrap();
+    content::Json(data.serialize())
+}
+
+#[post("/playlist", format = "application/json", data = "<data>")]
+fn post_playlist(data: Data,
+                 playlist: State<Mutex<Playlist>>,
+                 params: State<Mutex<PainterParams>>,
+                 painters: State<Vec<PainterInfo>>,
+                 check: State<ParamsCheck>,
+                 sender: State<Sender<Command>>) -> Result<(), Box<dyn Error>> {
+    let new_playlist = Playlist::deserialize(&read_body(data)?)?;
+    {
+        let current_params = params.lock().unwrap();
+        for entry in new_playlist.entries.iter() {
+            check_painters(&current_params.update_value(&entry.preset)?, &painters, &check)?;
+        }
+    }
+    let mut old_playlist = playlist.lock().unwrap();
+    *old_playlist = new_playlist.clone();
+    match new_playlist.save() {
         Err(e) => println!("Error writing to file: {}", e),
         Ok(()) => {}
     }
-    new_params.apply_dimming();
-    sender.send(new_params).unwrap();
+    sender.send(Command::SetPlaylist(new_playlist)).unwrap();
     Ok(())
 }
 
-pub fn rocket_server(params: PainterParams) -> Result<Receiver<PainterParams>, Box<dyn Error>> {
-    let (sender, receiver) = bounded::<PainterParams>(5);
+#[post("/playlist/start")]
+fn playlist_start(sender: State<Sender<Command>>) {
+    sender.send(Command::Playlist(PlaylistCommand::Start)).unwrap();
+}
+
+#[post("/playlist/stop")]
+fn playlist_stop(sender: State<Sender<Command>>) {
+    sender.send(Command::Playlist(PlaylistCommand::Stop)).unwrap();
+}
+
+#[post("/playlist/next")]
+fn playlist_next(sender: State<Sender<Command>>) {
+    sender.send(Command::Playlist(PlaylistCommand::Next)).unwrap();
+}
+
+#[post("/playlist/previous")]
+fn playlist_previous(sender: State<Sender<Command>>) {
+    sender.send(Command::Playlist(PlaylistCommand::Previous)).unwrap();
+}
+
+#[post("/sequence/play")]
+fn sequence_play(sender: State<Sender<Command>>) {
+    sender.send(Command::Sequence(SequenceCommand::Play)).unwrap();
+}
+
+#[post("/sequence/pause")]
+fn sequence_pause(sender: State<Sender<Command>>) {
+    sender.send(Command::Sequence(SequenceCommand::Pause)).unwrap();
+}
+
+#[post("/sequence/seek/<seconds>")]
+fn sequence_seek(seconds: f32, sender: State<Sender<Command>>) {
+    sender.send(Command::Sequence(SequenceCommand::Seek(seconds))).unwrap();
+}
+
+#[get("/power")]
+fn get_power(power: State<Arc<Mutex<PowerStatus>>>) -> content::Json<String> {
+    let data = power.lock().unwrap();
+    content::Json(serde_json::to_string(&*data).unwrap())
+}
+
+#[get("/frames")]
+fn get_frames(frames: State<Arc<Mutex<FrameStatus>>>) -> content::Json<String> {
+    let data = frames.lock().unwrap();
+    content::Json(serde_json::to_string(&*data).unwrap())
+}
+
+pub fn rocket_server(params: PainterParams, playlist: Playlist,
+                     painters: Vec<PainterInfo>, check: ParamsCheck, zones: Vec<String>,
+                     power: Arc<Mutex<PowerStatus>>, frames: Arc<Mutex<FrameStatus>>)
+                     -> Result<Receiver<Command>, Box<dyn Error>> {
+    let (sender, receiver) = bounded::<Command>(5);
     thread::spawn(move || {
         rocket::ignite()
             .manage(Mutex::new(params))
+            .manage(Mutex::new(playlist))
+            .manage(painters)
+            .manage(check)
+            .manage(zones)
+            .manage(power)
+            .manage(frames)
             .manage(sender)
-            .mount("/", routes![get, post]).launch();
+            .mount("/", routes![get, get_painters, post,
+                                get_zones, get_zone, post_zone, delete_zone,
+                                get_transition, post_transition,
+                                get_playlist, post_playlist, playlist_start, playlist_stop,
+                                playlist_next, playlist_previous,
+                                sequence_play, sequence_pause, sequence_seek,
+                                get_power, get_frames]).launch();
     });
 
     Ok(receiver)
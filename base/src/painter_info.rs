@@ -0,0 +1,9 @@
+use serde::Serialize;
+
+// Describes a painter that can be selected by id in PainterParams.
+#[derive(Copy, Clone, Debug, Serialize)]
+pub struct PainterInfo {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::error::Error;
 use serde::{Serialize, Deserialize};
 use serde_json;
@@ -6,12 +7,163 @@ use std::io::prelude::*;
 
 use crate::Color;
 
+// How a layer combines with the layers below it.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    Add,
+    Multiply,
+    Screen,
+    Max,
+    AlphaOver,  // Uses the layer's brightness as its alpha, so black is transparent.
+}
+
+impl Default for BlendMode {
+    fn default() -> Self { BlendMode::AlphaOver }
+}
+
+// Replacements for some of the PainterParams fields. Unset fields are left alone.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ParamOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fade: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_colors: Option<Vec<Color>>,
+}
+
+impl ParamOverrides {
+    pub fn apply(&self, params: &mut PainterParams) {
+        if let Some(speed) = self.speed {
+            params.speed = speed;
+        }
+        if let Some(fade) = self.fade {
+            params.fade = fade;
+        }
+        if let Some(color) = self.color {
+            params.color = color;
+        }
+        if let Some(secondary_colors) = &self.secondary_colors {
+            params.secondary_colors = secondary_colors.clone();
+            params.color_index = 0;
+        }
+    }
+}
+
+fn full_opacity() -> f32 { 1.0 }
+
+// A painter drawn on top of the main painter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Layer {
+    pub painter: String,
+    #[serde(default = "full_opacity")]
+    pub opacity: f32,
+    #[serde(default)]
+    pub blend: BlendMode,
+    #[serde(flatten)]
+    pub overrides: ParamOverrides,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionKind {
+    Cut,
+    Crossfade,
+    Wipe,
+    Dissolve,
+    FadeThroughBlack,
+}
+
+impl Default for TransitionKind {
+    fn default() -> Self { TransitionKind::Crossfade }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WipeDirection {
+    Down,
+    Up,
+    Left,
+    Right,
+}
+
+impl Default for WipeDirection {
+    fn default() -> Self { WipeDirection::Down }
+}
+
+fn default_transition_duration() -> f32 { 1.0 }
+
+// How to switch over when the painters change.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct TransitionParams {
+    #[serde(default)]
+    pub kind: TransitionKind,
+    #[serde(default = "default_transition_duration")]
+    pub duration: f32,  // Seconds.
+    #[serde(default)]
+    pub direction: WipeDirection,  // Only used by wipes.
+}
+
+impl Default for TransitionParams {
+    fn default() -> Self {
+        TransitionParams { kind: TransitionKind::default(), duration: default_transition_duration(),
+                           direction: WipeDirection::default() }
+    }
+}
+
+impl TransitionParams {
+    pub fn deserialize(string: &str) -> Result<Self, Box<dyn Error>> {
+        let t: TransitionParams = serde_json::from_str(string)?;
+        return Ok(t);
+    }
+}
+
+fn default_start_channel() -> usize { 1 }
+
+// An xLights sequence for the fseq painter.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SequenceParams {
+    #[serde(default)]
+    pub file: String,  // Path to the .fseq file.
+    // Channel holding the red of the first LED in the layout, counting from 1 like xLights does.
+    // Each LED after it takes the next three channels, following the wiring through all segments.
+    #[serde(default = "default_start_channel")]
+    pub start_channel: usize,
+}
+
+impl Default for SequenceParams {
+    fn default() -> Self {
+        SequenceParams { file: String::new(), start_channel: default_start_channel() }
+    }
+}
+
+// Settings for one zone of the garment, named after its layout segment. Anything unset is
+// inherited from the top level PainterParams.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Zone {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub painter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layers: Option<Vec<Layer>>,
+    #[serde(flatten)]
+    pub overrides: ParamOverrides,
+}
+
+impl Zone {
+    pub fn deserialize(string: &str) -> Result<Self, Box<dyn Error>> {
+        let z: Zone = serde_json::from_str(string)?;
+        return Ok(z);
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PainterParams {
     pub painter: String,
     pub global_brightness: f32,
-    pub speed: f32,
+    pub speed: f32,  // LEDs per 30ms reference tick. fade is also per tick.
     pub color: Color,
     pub secondary_colors: Vec<Color>,
     pub fade: f32,
@@ -20,6 +172,15 @@ pub struct PainterParams {
     #[serde(skip)]
     pub color_index: usize,  // Shouldn't be public but w/e.
     pub belt_only: bool,  // This is super specific but I'm out of time to do it elegantly.
+    // Painters composited over `painter`, bottom first.
+    #[serde(default)]
+    pub layers: Vec<Layer>,
+    #[serde(default)]
+    pub zones: BTreeMap<String, Zone>,
+    #[serde(default)]
+    pub transition: TransitionParams,
+    #[serde(default)]
+    pub sequence: SequenceParams,
 }
 
 impl PainterParams {
@@ -31,11 +192,61 @@ impl PainterParams {
         let p: PainterParams = serde_json::from_str(string)?;
         return Ok(p);
     }
-    pub fn apply_dimming(&mut self) {
-        self.color *= self.global_brightness;
-        for idx in 0..self.secondary_colors.len() {
-            self.secondary_colors[idx] *= self.global_brightness;
+    // Applies a JSON object holding some of the fields on top of these params.
+    pub fn update(&self, string: &str) -> Result<Self, Box<dyn Error>> {
+        let changes: serde_json::Value = serde_json::from_str(string)?;
+        self.update_value(&changes)
+    }
+    pub fn update_value(&self, changes: &serde_json::Value) -> Result<Self, Box<dyn Error>> {
+        let mut value = serde_json::to_value(self)?;
+        match (value.as_object_mut(), changes.as_object()) {
+            (Some(fields), Some(changed_fields)) => {
+                for (key, changed) in changed_fields.iter() {
+                    fields.insert(key.clone(), changed.clone());
+                }
+            }
+            _ => return Err("Expected a JSON object".into()),
         }
+        let p: PainterParams = serde_json::from_value(value)?;
+        return Ok(p);
+    }
+    // Every painter id these params would run, in any zone.
+    pub fn painter_ids(&self) -> Vec<&str> {
+        let mut ids = vec![self.painter.as_str()];
+        ids.extend(self.layers.iter().map(|layer| layer.painter.as_str()));
+        for zone in self.zones.values() {
+            ids.extend(zone.painter.iter().map(|painter| painter.as_str()));
+            ids.extend(zone.layers.iter().flatten().map(|layer| layer.painter.as_str()));
+        }
+        ids
+    }
+    // The params for one zone, with its settings applied on top of the defaults.
+    pub fn zone_params(&self, zone: &str) -> PainterParams {
+        let mut params = self.clone();
+        params.zones = BTreeMap::new();
+        if let Some(settings) = self.zones.get(zone) {
+            if let Some(painter) = &settings.painter {
+                params.painter = painter.clone();
+            }
+            if let Some(layers) = &settings.layers {
+                params.layers = layers.clone();
+            }
+            settings.overrides.apply(&mut params);
+        }
+        params
+    }
+    // The params for the bottom layer, without the layers above it.
+    pub fn base_params(&self) -> PainterParams {
+        let mut params = self.clone();
+        params.layers = Vec::new();
+        params
+    }
+    // The params for a layer, which inherits anything it doesn't override.
+    pub fn layer_params(&self, layer: &Layer) -> PainterParams {
+        let mut params = self.base_params();
+        params.painter = layer.painter.clone();
+        layer.overrides.apply(&mut params);
+        params
     }
     pub fn load() -> Result<Self, Box<dyn Error>> {
         let mut file = File::open("last_params.json")?;
@@ -0,0 +1,56 @@
+use std::error::Error;
+use serde::{Serialize, Deserialize};
+use serde_json;
+use std::fs::File;
+use std::io::prelude::*;
+
+// One step of a playlist. The preset holds some PainterParams fields, which are applied on top
+// of the params set through the API while the step is playing.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub preset: serde_json::Value,
+    // Seconds to play this step for, instead of the playlist's duration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f32>,
+}
+
+fn default_duration() -> f32 { 60.0 }
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Playlist {
+    pub entries: Vec<PlaylistEntry>,
+    #[serde(default = "default_duration")]
+    pub duration: f32,  // Seconds per step.
+    #[serde(default)]
+    pub shuffle: bool,
+}
+
+impl Playlist {
+    pub fn new() -> Self {
+        Playlist { entries: Vec::new(), duration: default_duration(), shuffle: false }
+    }
+    pub fn serialize(&self) -> String {
+        return serde_json::to_string(self).unwrap();
+    }
+    pub fn deserialize(string: &str) -> Result<Self, Box<dyn Error>> {
+        let p: Playlist = serde_json::from_str(string)?;
+        let durations = p.entries.iter().filter_map(|entry| entry.duration);
+        if let Some(duration) = std::iter::once(p.duration).chain(durations)
+            .find(|duration| !duration.is_finite() || *duration < 0.0) {
+            return Err(format!("Invalid playlist duration: {}", duration).into());
+        }
+        return Ok(p);
+    }
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let mut file = File::open("playlist.json")?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        return Self::deserialize(&contents);
+    }
+    pub fn save(&self) -> std::io::Result<()> {
+        let contents = self.serialize();
+        let mut file = File::create("playlist.json")?;
+        file.write_all(contents.as_bytes())?;
+        Ok(())
+    }
+}
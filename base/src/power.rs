@@ -0,0 +1,34 @@
+use serde::{Serialize, Deserialize};
+
+fn default_channel_ma() -> f32 { 20.0 }
+fn default_idle_ma() -> f32 { 1.0 }
+
+// How much current the LEDs may draw. APA102s take about 20mA per channel at full brightness,
+// plus a little for each LED even when it is off.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct PowerConfig {
+    // Frames estimated to draw more than this are dimmed to fit. Unset only measures.
+    #[serde(default)]
+    pub budget_ma: Option<f32>,
+    #[serde(default = "default_channel_ma")]
+    pub channel_ma: f32,  // One channel at 255.
+    #[serde(default = "default_idle_ma")]
+    pub idle_ma: f32,  // Each LED while dark.
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        PowerConfig { budget_ma: None, channel_ma: default_channel_ma(), idle_ma: default_idle_ma() }
+    }
+}
+
+// The power limiter's latest numbers, served at /power.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PowerStatus {
+    pub budget_ma: Option<f32>,
+    pub estimated_ma: f32,  // What the last frame would have drawn as painted.
+    pub output_ma: f32,     // What it draws after limiting.
+    pub scale: f32,         // Brightness applied to the last frame, 1.0 when not limiting.
+    pub limited_frames: u64,
+    pub limit_events: u64,  // Times the limiter started dimming.
+}
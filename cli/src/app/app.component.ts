@@ -8,6 +8,12 @@ interface Color {
   b: number,
 }
 
+interface PainterInfo {
+  id: string,
+  name: string,
+  description: string,
+}
+
 interface PainterParams {
   painter: string,
   global_brightness: number,
@@ -36,14 +42,7 @@ export class AppComponent  {
   private initialParams: PainterParams;
   private server: string = "/api";
 
-  painters = [
-    {id: 'hex', text: "Hex"},
-    {id: "line", text: "Line"},
-    {id: "pulse", text: "Pulse"},
-    {id: "rain", text: "Rain"},
-    {id: "fade", text: "Fade"},
-    {id: "disco", text: "Disco"},
-  ];
+  painters: PainterInfo[] = [];
 
   constructor(
     private formBuilder: FormBuilder,
@@ -51,6 +50,9 @@ export class AppComponent  {
   }
 
   ngOnInit() {
+    this.http.get(this.server + "/painters").subscribe((data: PainterInfo[]) => {
+      this.painters = data;
+    });
     this.http.get(this.server).subscribe((data: PainterParams) => {
       this.form = this.formBuilder.group({
         painter: [data.painter],
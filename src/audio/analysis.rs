@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+pub const BANDS: usize = 8;
+
+// Upper edge of each band in Hz. The first band starts at BAND_EDGES[0].
+const BAND_EDGES: [f32; BANDS + 1] = [30.0, 80.0, 160.0, 320.0, 640.0, 1280.0, 2560.0, 5120.0, 10240.0];
+const FFT_SIZE: usize = 1024;
+const HOP: usize = 512;
+// How much bigger than its recent average the spectral flux must be to count as a beat.
+const BEAT_THRESHOLD: f32 = 1.6;
+const MIN_BEAT_SECONDS: f32 = 0.25;
+// Quieter than this, there is no music to find beats in.
+const BEAT_LOUDNESS: f32 = 0.1;
+
+// What the music is doing right now. Painters get a copy every frame in FrameTime.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AudioFeatures {
+    pub active: bool,          // False when there is no audio input.
+    pub loudness: f32,         // From 0.0 at -60 dBFS to 1.0 at full scale.
+    pub bands: [f32; BANDS],   // Energy per band, lowest first, relative to recent peaks.
+    pub onset: f32,            // Spectral flux relative to its recent average.
+    pub beats: u64,            // Beats so far. A painter has a new beat when this changes.
+}
+
+impl AudioFeatures {
+    // Whether there is music loud enough to have beats. Painters that follow the beat should
+    // go back to their own speed when there isn't.
+    pub fn playing(&self) -> bool { self.active && self.loudness > BEAT_LOUDNESS }
+}
+
+// In-place iterative radix-2 FFT. Both slices must be the same power of two long.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f32;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (w_im, w_re) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + length / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        length <<= 1;
+    }
+}
+
+// Turns a stream of samples into AudioFeatures, one FFT every HOP samples.
+pub struct Analyzer {
+    sample_rate: u32,
+    window: Vec<f32>,
+    samples: VecDeque<f32>,  // The last FFT_SIZE samples.
+    pending: usize,  // Samples since the last FFT.
+    previous_spectrum: Vec<f32>,
+    band_peaks: [f32; BANDS],
+    flux_average: f32,
+    seconds: f32,
+    last_beat: f32,
+    features: AudioFeatures,
+}
+
+impl Analyzer {
+    pub fn new(sample_rate: u32) -> Self {
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        let mut features = AudioFeatures::default();
+        features.active = true;
+        Analyzer { sample_rate: sample_rate, window: window, samples: vec![0.0; FFT_SIZE].into(), pending: 0,
+                   previous_spectrum: vec![0.0; FFT_SIZE / 2], band_peaks: [1e-6; BANDS],
+                   flux_average: 0.0, seconds: 0.0, last_beat: 0.0,
+                   features: features }
+    }
+
+    pub fn features(&self) -> AudioFeatures { self.features }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            self.samples.pop_front();
+            self.samples.push_back(sample);
+            self.pending += 1;
+            if self.pending >= HOP {
+                self.pending = 0;
+                self.analyze();
+            }
+        }
+    }
+
+    fn analyze(&mut self) {
+        self.seconds += HOP as f32 / self.sample_rate as f32;
+
+        let rms = (self.samples.iter().map(|s| s * s).sum::<f32>() / FFT_SIZE as f32).sqrt();
+        let decibels = 20.0 * rms.max(1e-6).log10();
+        self.features.loudness = ((decibels + 60.0) / 60.0).max(0.0).min(1.0);
+
+        let mut re: Vec<f32> = self.samples.iter().zip(self.window.iter()).map(|(s, w)| s * w).collect();
+        let mut im = vec![0.0; FFT_SIZE];
+        fft(&mut re, &mut im);
+        let spectrum: Vec<f32> = (0..FFT_SIZE / 2).map(|i| (re[i] * re[i] + im[i] * im[i]).sqrt()).collect();
+
+        let bin_hz = self.sample_rate as f32 / FFT_SIZE as f32;
+        for band in 0..BANDS {
+            let low = ((BAND_EDGES[band] / bin_hz) as usize).min(spectrum.len());
+            let high = ((BAND_EDGES[band + 1] / bin_hz) as usize).max(low + 1).min(spectrum.len());
+            // Bands above half the sample rate are empty at low sample rates.
+            let energy = if low < high {
+                spectrum[low..high].iter().sum::<f32>() / (high - low) as f32
+            } else {
+                0.0
+            };
+            // Compare with a slowly decaying peak, so quiet and loud music both use the range.
+            self.band_peaks[band] = energy.max(self.band_peaks[band] * 0.995).max(1e-6);
+            self.features.bands[band] = energy / self.band_peaks[band];
+        }
+
+        let flux: f32 = spectrum.iter().zip(self.previous_spectrum.iter())
+            .map(|(now, before)| (now - before).max(0.0))
+            .sum();
+        self.previous_spectrum = spectrum;
+        self.features.onset = flux / self.flux_average.max(1e-6);
+        self.flux_average = self.flux_average * 0.95 + flux * 0.05;
+
+        let since_beat = self.seconds - self.last_beat;
+        if self.features.onset > BEAT_THRESHOLD && since_beat > MIN_BEAT_SECONDS && self.features.loudness > BEAT_LOUDNESS {
+            self.features.beats += 1;
+            self.last_beat = self.seconds;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bands_above_half_the_sample_rate_are_empty() {
+        let sample_rate = 8000;
+        let mut analyzer = Analyzer::new(sample_rate);
+        let samples: Vec<f32> = (0..FFT_SIZE * 4)
+            .map(|i| (2.0 * PI * 1000.0 * i as f32 / sample_rate as f32).sin())
+            .collect();
+        analyzer.push(&samples);
+        let features = analyzer.features();
+        // 1 kHz is in the 640-1280 Hz band, and everything from 5120 Hz up is past 4 kHz.
+        assert!(features.bands[4] > 0.5);
+        assert_eq!(features.bands[7], 0.0);
+    }
+}
@@ -0,0 +1,84 @@
+/**
+ * Listens to music so painters can react to it. A source is read and analyzed on its own thread,
+ * and the latest AudioFeatures are handed to the painters with each frame.
+ */
+use std::error::Error;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam_channel::bounded;
+
+mod analysis;
+mod source;
+
+pub use analysis::{Analyzer, AudioFeatures};
+pub use source::{AudioSource, PcmSource, WavSource};
+#[cfg(feature = "alsa")]
+pub use source::AlsaSource;
+
+pub type SharedFeatures = Arc<Mutex<AudioFeatures>>;
+
+const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+// Opens a source described as "wav:<path>", "stdin[:<sample rate>]" or "alsa[:<device>]".
+pub fn open_source(spec: &str) -> Result<Box<dyn AudioSource>, Box<dyn Error>> {
+    let mut parts = spec.splitn(2, ':');
+    let kind = parts.next().unwrap_or("");
+    let argument = parts.next();
+    match kind {
+        "wav" => Ok(Box::new(WavSource::open(argument.ok_or("wav needs a path")?)?)),
+        "stdin" => {
+            let sample_rate = match argument {
+                Some(rate) => rate.parse()?,
+                None => DEFAULT_SAMPLE_RATE,
+            };
+            Ok(Box::new(PcmSource::new(io::stdin(), sample_rate)))
+        },
+        #[cfg(feature = "alsa")]
+        "alsa" => Ok(Box::new(AlsaSource::open(argument.unwrap_or("default"), DEFAULT_SAMPLE_RATE)?)),
+        _ => Err(format!("Unknown audio source: {}", spec).into()),
+    }
+}
+
+// Opens the source described by spec (see open_source) and analyzes it on its own thread.
+pub fn start(spec: &str) -> Result<SharedFeatures, Box<dyn Error>> {
+    let spec = spec.to_string();
+    let features = Arc::new(Mutex::new(AudioFeatures::default()));
+    let shared = features.clone();
+    let (opened_sender, opened) = bounded(1);
+    thread::spawn(move || {
+        let mut source = match open_source(&spec) {
+            Ok(source) => source,
+            Err(e) => {
+                let _ = opened_sender.send(Err(e.to_string()));
+                return;
+            },
+        };
+        let mut analyzer = Analyzer::new(source.sample_rate());
+        *shared.lock().unwrap() = analyzer.features();
+        let _ = opened_sender.send(Ok(()));
+        let mut buffer = vec![0.0; 512];
+        let chunk = Duration::from_secs_f32(buffer.len() as f32 / source.sample_rate() as f32);
+        loop {
+            let count = match source.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(count) => count,
+                Err(e) => {
+                    println!("Audio input failed: {}", e);
+                    break;
+                }
+            };
+            analyzer.push(&buffer[..count]);
+            *shared.lock().unwrap() = analyzer.features();
+            if !source.live() {
+                thread::sleep(chunk);
+            }
+        }
+        println!("Audio input ended");
+        shared.lock().unwrap().active = false;
+    });
+    opened.recv()??;
+    Ok(features)
+}
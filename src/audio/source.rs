@@ -0,0 +1,137 @@
+use std::error::Error;
+use std::io::{self, Read};
+
+// Somewhere to read mono audio from, as samples between -1.0 and 1.0. Sources are opened on the
+// thread that reads them, so they don't need to be Send.
+pub trait AudioSource {
+    fn sample_rate(&self) -> u32;
+    // Whether samples arrive in real time. Other sources are read at the sample rate.
+    fn live(&self) -> bool;
+    // Fills some of buf and returns how many samples were read, or 0 at the end of the stream.
+    fn read(&mut self, buf: &mut [f32]) -> io::Result<usize>;
+}
+
+// A WAV file, mixed down to mono.
+pub struct WavSource {
+    samples: Vec<f32>,
+    position: usize,
+    sample_rate: u32,
+}
+
+impl WavSource {
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+        let interleaved: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+                reader.samples::<i32>().map(|sample| sample.map(|s| s as f32 / scale))
+                    .collect::<Result<_, _>>()?
+            },
+        };
+        let channels = spec.channels as usize;
+        let samples = interleaved.chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+        Ok(WavSource { samples: samples, position: 0, sample_rate: spec.sample_rate })
+    }
+}
+
+impl AudioSource for WavSource {
+    fn sample_rate(&self) -> u32 { self.sample_rate }
+    fn live(&self) -> bool { false }
+    fn read(&mut self, buf: &mut [f32]) -> io::Result<usize> {
+        let count = buf.len().min(self.samples.len() - self.position);
+        buf[..count].copy_from_slice(&self.samples[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+// Raw signed 16 bit little endian mono PCM, e.g. `arecord -f S16_LE -c 1 -r 44100 | wavesuit`.
+pub struct PcmSource<R: Read> {
+    reader: R,
+    sample_rate: u32,
+    bytes: Vec<u8>,
+}
+
+impl<R: Read> PcmSource<R> {
+    pub fn new(reader: R, sample_rate: u32) -> Self {
+        PcmSource { reader: reader, sample_rate: sample_rate, bytes: Vec::new() }
+    }
+}
+
+impl<R: Read> AudioSource for PcmSource<R> {
+    fn sample_rate(&self) -> u32 { self.sample_rate }
+    fn live(&self) -> bool { true }
+    fn read(&mut self, buf: &mut [f32]) -> io::Result<usize> {
+        self.bytes.resize(buf.len() * 2, 0);
+        let mut filled = 0;
+        // Read whole samples only.
+        while filled == 0 || filled % 2 != 0 {
+            let count = self.reader.read(&mut self.bytes[filled..])?;
+            if count == 0 {
+                break;
+            }
+            filled += count;
+        }
+        let samples = filled / 2;
+        for i in 0..samples {
+            buf[i] = i16::from_le_bytes([self.bytes[i * 2], self.bytes[i * 2 + 1]]) as f32 / 32768.0;
+        }
+        Ok(samples)
+    }
+}
+
+// Captures from an ALSA device. "default" goes through PulseAudio when it is running.
+#[cfg(feature = "alsa")]
+pub struct AlsaSource {
+    pcm: alsa::PCM,
+    sample_rate: u32,
+    buffer: Vec<i16>,
+}
+
+#[cfg(feature = "alsa")]
+impl AlsaSource {
+    pub fn open(device: &str, sample_rate: u32) -> Result<Self, Box<dyn Error>> {
+        use alsa::pcm::{Access, Format, HwParams};
+        let pcm = alsa::PCM::new(device, alsa::Direction::Capture, false)?;
+        let sample_rate = {
+            let hwp = HwParams::any(&pcm)?;
+            hwp.set_channels(1)?;
+            hwp.set_rate(sample_rate, alsa::ValueOr::Nearest)?;
+            hwp.set_format(Format::s16())?;
+            hwp.set_access(Access::RWInterleaved)?;
+            pcm.hw_params(&hwp)?;
+            hwp.get_rate()?
+        };
+        pcm.start()?;
+        Ok(AlsaSource { pcm: pcm, sample_rate: sample_rate, buffer: Vec::new() })
+    }
+}
+
+#[cfg(feature = "alsa")]
+impl AudioSource for AlsaSource {
+    fn sample_rate(&self) -> u32 { self.sample_rate }
+    fn live(&self) -> bool { true }
+    fn read(&mut self, buf: &mut [f32]) -> io::Result<usize> {
+        self.buffer.resize(buf.len(), 0);
+        let count = loop {
+            let result = {
+                let io = self.pcm.io_i16().map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                io.readi(&mut self.buffer)
+            };
+            match result {
+                Ok(count) => break count,
+                // Recover from overruns rather than giving up on the stream. Errors that can't be
+                // recovered from are returned by try_recover.
+                Err(e) => self.pcm.try_recover(e, true).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?,
+            }
+        };
+        for i in 0..count {
+            buf[i] = self.buffer[i] as f32 / 32768.0;
+        }
+        Ok(count)
+    }
+}
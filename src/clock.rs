@@ -0,0 +1,51 @@
+use std::time::Instant;
+
+use crate::audio::{AudioFeatures, SharedFeatures};
+
+// Speeds and fades are defined per reference tick. The painters were tuned on the default
+// runner, which ticks every 30ms, so a speed of 1.0 moves about 33 LEDs a second.
+pub const TICK_SECONDS: f32 = 0.03;
+
+// Longest step a frame can take, so a stall doesn't make everything jump.
+const MAX_DELTA_SECONDS: f32 = 0.25;
+
+// When a frame is being painted.
+#[derive(Copy, Clone, Debug)]
+pub struct FrameTime {
+    pub seconds: f64,  // Since the clock started.
+    pub delta: f32,    // Seconds since the previous frame.
+    pub audio: AudioFeatures,
+}
+
+impl FrameTime {
+    // Reference ticks since the previous frame. Multiply a speed by this to get how far to move.
+    pub fn ticks(&self) -> f32 { self.delta / TICK_SECONDS }
+
+    // The multiplier for this frame to fade at `fade` per reference tick.
+    pub fn fade(&self, fade: f32) -> f32 { fade.powf(self.ticks()) }
+}
+
+// Shared by everything painted in a frame, so painters stay in step with each other.
+pub struct Clock {
+    start: Instant,
+    last: Instant,
+    audio: Option<SharedFeatures>,
+}
+
+impl Clock {
+    pub fn new(audio: Option<SharedFeatures>) -> Self {
+        let now = Instant::now();
+        Clock { start: now, last: now, audio: audio }
+    }
+
+    pub fn tick(&mut self) -> FrameTime {
+        let now = Instant::now();
+        let delta = now.duration_since(self.last).as_secs_f32().min(MAX_DELTA_SECONDS);
+        self.last = now;
+        let audio = match &self.audio {
+            Some(features) => *features.lock().unwrap(),
+            None => AudioFeatures::default(),
+        };
+        FrameTime { seconds: now.duration_since(self.start).as_secs_f64(), delta: delta, audio: audio }
+    }
+}
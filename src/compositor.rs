@@ -0,0 +1,71 @@
+use base::{BlendMode, FloatColor, Layer, PainterParams, SequenceCommand};
+
+use crate::clock::FrameTime;
+use crate::painter::Painter;
+
+// Combines one channel of a layer (above) with what is already painted (below). Both are 0-1.
+fn blend_channel(mode: BlendMode, below: f32, above: f32) -> f32 {
+    match mode {
+        BlendMode::Add => below + above,
+        BlendMode::Multiply => below * above,
+        BlendMode::Screen => 1.0 - (1.0 - below) * (1.0 - above),
+        BlendMode::Max => below.max(above),
+        BlendMode::AlphaOver => above,
+    }
+}
+
+fn blend(mode: BlendMode, opacity: f32, below: FloatColor, above: FloatColor) -> FloatColor {
+    let mut alpha = opacity;
+    if mode == BlendMode::AlphaOver {
+        alpha *= (above.r.max(above.g).max(above.b) / 255.0).min(1.0);
+    }
+    let channel = |below: f32, above: f32| -> f32 {
+        let below = below / 255.0;
+        let blended = blend_channel(mode, below, above / 255.0);
+        let mixed = below + (blended - below) * alpha;
+        mixed.max(0.0).min(1.0) * 255.0
+    };
+    FloatColor {r: channel(below.r, above.r), g: channel(below.g, above.g), b: channel(below.b, above.b)}
+}
+
+// Runs a stack of painters over the same area and merges their output, bottom first.
+pub struct LayeredPainter {
+    base: Box<dyn Painter>,
+    layers: Vec<(Box<dyn Painter>, Layer)>,
+    leds: Vec<FloatColor>,
+}
+
+impl LayeredPainter {
+    pub fn new(base: Box<dyn Painter>, layers: Vec<(Box<dyn Painter>, Layer)>) -> Self {
+        let length = base.leds().len();
+        LayeredPainter { base: base, layers: layers, leds: vec![FloatColor::black(); length] }
+    }
+}
+
+impl Painter for LayeredPainter {
+    fn paint(&mut self, time: &FrameTime) {
+        self.base.paint(time);
+        self.leds.copy_from_slice(self.base.leds());
+        for (painter, layer) in self.layers.iter_mut() {
+            painter.paint(time);
+            for (led, &above) in self.leds.iter_mut().zip(painter.leds().iter()) {
+                *led = blend(layer.blend, layer.opacity, *led, above);
+            }
+        }
+    }
+    fn leds(&self) -> &[FloatColor] { &self.leds }
+    // Assumes the layers' painters haven't changed; make a new LayeredPainter if they have.
+    fn set_params(&mut self, params: PainterParams) {
+        for ((painter, layer), new_layer) in self.layers.iter_mut().zip(params.layers.iter()) {
+            painter.set_params(params.layer_params(new_layer));
+            *layer = new_layer.clone();
+        }
+        self.base.set_params(params.base_params());
+    }
+    fn control(&mut self, command: SequenceCommand) {
+        self.base.control(command);
+        for (painter, _) in self.layers.iter_mut() {
+            painter.control(command);
+        }
+    }
+}
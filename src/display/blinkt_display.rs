@@ -1,30 +1,39 @@
 /**
  * The "real" output Display for wavesuit. This uses blinkt to write SPI to a chain of
- * APA102 LED's.
+ * APA102 LED's, using their brightness field for extra precision when dim, and dithering what
+ * is left over.
  */
 use std::error::Error;
 
+use base::apa102::{Apa102Pixel, MAX_BRIGHTNESS};
+use base::{Color, Dither, FloatColor};
+
 use crate::display::Display;
 
-#[cfg(target_arch = "arm")]
 use blinkt::Blinkt;
 
 pub struct BlinktDisplay {
     blinkt: Blinkt,
+    dither: Dither,
 }
 
 impl Display for BlinktDisplay {
     fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8) {
-        self.blinkt.set_pixel(index, r, g, b);
+        self.set_color(index, FloatColor::from(Color{r: r, g: g, b: b}));
+    }
+    fn set_color(&mut self, index: usize, color: FloatColor) {
+        let pixel = Apa102Pixel::dithered(&mut self.dither, index, color);
+        // blinkt rounds brightness down to 5 bits, so aim for the middle of the step.
+        let brightness = (pixel.brightness as f32 + 0.5) / MAX_BRIGHTNESS as f32;
+        self.blinkt.set_pixel_rgbb(index, pixel.r, pixel.g, pixel.b, brightness);
     }
     fn show(&mut self) -> Result<(), Box<dyn Error>> {
         self.blinkt.show()?;
         Ok(())
     }
-    fn set_offset(&mut self, count: usize) {}
 }
 
 pub fn make_display(pixels: usize) -> Result<BlinktDisplay, Box<dyn Error>> {
     println!("Using a blinkt display");
-    Ok(BlinktDisplay {blinkt: Blinkt::with_spi(8_000_000, pixels)?})
+    Ok(BlinktDisplay {blinkt: Blinkt::with_spi(8_000_000, pixels)?, dither: Dither::new(pixels)})
 }
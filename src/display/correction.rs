@@ -0,0 +1,64 @@
+/**
+ * Turns painted colors into LED values as they are shown: gamma, so fades look even instead of
+ * jumping out of black and washing out in the middle, then per-channel gains for white balance
+ * and the global brightness. Segments can have their own calibration, for strips from a
+ * different batch.
+ *
+ * The result keeps its fractional part, for the dithering further down to carry over.
+ */
+use std::error::Error;
+
+use base::{Calibration, ColorCorrection, FloatColor, Layout};
+
+use crate::display::Display;
+
+pub struct ColorCorrector {
+    display: Box<dyn Display>,
+    calibrations: Vec<Calibration>,
+    led_calibrations: Vec<usize>,  // Index into calibrations for each display LED.
+    brightness: f32,
+}
+
+impl ColorCorrector {
+    pub fn new(display: Box<dyn Display>, layout: &Layout, correction: &ColorCorrection) -> Self {
+        let mut calibrations = vec![correction.calibration];
+        let mut led_calibrations = Vec::new();
+        for segment in layout.display_segments() {
+            let calibration = correction.for_segment(&segment.name);
+            let index = match calibrations.iter().position(|&known| known == calibration) {
+                Some(index) => index,
+                None => {
+                    calibrations.push(calibration);
+                    calibrations.len() - 1
+                },
+            };
+            led_calibrations.push(index);
+        }
+        for name in correction.segments.keys() {
+            if !layout.segments.iter().any(|segment| &segment.name == name) {
+                println!("No segment {} to calibrate", name);
+            }
+        }
+        ColorCorrector { display: display, calibrations: calibrations, led_calibrations: led_calibrations,
+                         brightness: 1.0 }
+    }
+}
+
+impl Display for ColorCorrector {
+    fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8) {
+        self.set_color(index, FloatColor::from(base::Color{r: r, g: g, b: b}));
+    }
+    fn set_color(&mut self, index: usize, color: FloatColor) {
+        let calibration = &self.calibrations[self.led_calibrations.get(index).cloned().unwrap_or(0)];
+        let corrected = FloatColor { r: calibration.correct(0, color.r), g: calibration.correct(1, color.g),
+                                     b: calibration.correct(2, color.b) };
+        self.display.set_color(index, corrected * self.brightness);
+    }
+    fn show(&mut self) -> Result<(), Box<dyn Error>> {
+        self.display.show()
+    }
+    // Scales every LED value after correction, so it scales the current drawn too.
+    fn set_brightness(&mut self, brightness: f32) {
+        self.brightness = brightness;
+    }
+}
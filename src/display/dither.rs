@@ -0,0 +1,37 @@
+/**
+ * Rounds colors to the LEDs' 8 bits with temporal dithering (see base::Dither), for displays
+ * that can't take the extra precision themselves.
+ */
+use std::error::Error;
+
+use base::{Dither, FloatColor};
+
+use crate::display::Display;
+
+pub struct DitheringDisplay {
+    display: Box<dyn Display>,
+    dither: Dither,
+}
+
+impl DitheringDisplay {
+    pub fn new(display: Box<dyn Display>, pixels: usize) -> Self {
+        DitheringDisplay { display: display, dither: Dither::new(pixels) }
+    }
+}
+
+impl Display for DitheringDisplay {
+    fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8) {
+        self.dither.reset(index);
+        self.display.set_pixel(index, r, g, b);
+    }
+    fn set_color(&mut self, index: usize, color: FloatColor) {
+        let shown = self.dither.pixel(index, color, |wanted| {
+            let shown = wanted.round();
+            (shown, FloatColor::from(shown))
+        });
+        self.display.set_pixel(index, shown.r, shown.g, shown.b);
+    }
+    fn show(&mut self) -> Result<(), Box<dyn Error>> {
+        self.display.show()
+    }
+}
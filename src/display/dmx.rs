@@ -0,0 +1,197 @@
+/**
+ * Sends frames as DMX universes over UDP, using E1.31 (sACN) or Art-Net, so stage fixtures and
+ * pixel walls can mirror the suit.
+ *
+ * Each universe carries 170 RGB pixels (510 of its 512 channels), so the display is split over
+ * as many consecutive universes as it needs, starting from a configurable one. Packets go to one
+ * host (unicast) or to everyone listening: E1.31 uses its multicast group for each universe, and
+ * Art-Net, which has no multicast, broadcasts on the local network.
+ */
+use std::error::Error;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, ToSocketAddrs, UdpSocket};
+
+use rand::prelude::*;
+
+use crate::display::Display;
+
+const PIXELS_PER_UNIVERSE: usize = 170;
+const E131_PORT: u16 = 5568;
+const ARTNET_PORT: u16 = 6454;
+const SOURCE_NAME: &str = "wavesuit";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Protocol {
+    E131,
+    ArtNet,
+}
+
+#[derive(Clone, Debug)]
+pub enum Destination {
+    Unicast(SocketAddr),
+    Multicast,
+}
+
+impl Protocol {
+    fn port(&self) -> u16 {
+        match self {
+            Protocol::E131 => E131_PORT,
+            Protocol::ArtNet => ARTNET_PORT,
+        }
+    }
+}
+
+pub struct DmxDisplay {
+    protocol: Protocol,
+    destination: Destination,
+    socket: UdpSocket,
+    start_universe: u16,
+    pixels: Vec<u8>,  // RGB.
+    sequences: Vec<u8>,  // Next sequence number for each universe.
+    cid: [u8; 16],  // E1.31 source id, new each run.
+}
+
+// Parses a destination: "multicast" or host[:port].
+pub fn parse_destination(protocol: Protocol, destination: &str) -> Result<Destination, Box<dyn Error>> {
+    if destination == "multicast" {
+        return Ok(Destination::Multicast);
+    }
+    let address = if destination.contains(':') {
+        destination.to_string()
+    } else {
+        format!("{}:{}", destination, protocol.port())
+    };
+    match address.to_socket_addrs()?.next() {
+        Some(address) => Ok(Destination::Unicast(address)),
+        None => Err(format!("No address for {}", destination).into()),
+    }
+}
+
+impl DmxDisplay {
+    pub fn new(protocol: Protocol, destination: Destination, start_universe: u16, pixels: usize)
+               -> Result<Self, Box<dyn Error>> {
+        if protocol == Protocol::E131 && start_universe == 0 {
+            return Err("E1.31 universes start at 1".into());
+        }
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        if protocol == Protocol::ArtNet {
+            if let Destination::Multicast = destination {
+                socket.set_broadcast(true)?;
+            }
+        }
+        // Sending should never hold up the frame; drop packets instead.
+        socket.set_nonblocking(true)?;
+        let universes = (pixels + PIXELS_PER_UNIVERSE - 1) / PIXELS_PER_UNIVERSE;
+        let first_sequence = if protocol == Protocol::ArtNet {1} else {0};
+        println!("Sending {:?} to {:?}, universes {} to {}", protocol, destination,
+                 start_universe, start_universe as usize + universes.max(1) - 1);
+        Ok(DmxDisplay { protocol: protocol, destination: destination, socket: socket,
+                        start_universe: start_universe, pixels: vec![0; pixels * 3],
+                        sequences: vec![first_sequence; universes], cid: rand::thread_rng().gen() })
+    }
+
+    fn address(&self, universe: u16) -> SocketAddr {
+        match (&self.destination, self.protocol) {
+            (Destination::Unicast(address), _) => *address,
+            (Destination::Multicast, Protocol::E131) => {
+                let [high, low] = universe.to_be_bytes();
+                SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(239, 255, high, low), E131_PORT))
+            },
+            (Destination::Multicast, Protocol::ArtNet) => {
+                SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::BROADCAST, ARTNET_PORT))
+            },
+        }
+    }
+
+    fn packet(&self, universe: u16, sequence: u8, data: &[u8]) -> Vec<u8> {
+        match self.protocol {
+            Protocol::E131 => e131_packet(&self.cid, universe, sequence, data),
+            Protocol::ArtNet => artnet_packet(universe, sequence, data),
+        }
+    }
+}
+
+impl Display for DmxDisplay {
+    fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8) {
+        let at = index * 3;
+        if at + 3 <= self.pixels.len() {
+            self.pixels[at..at + 3].copy_from_slice(&[r, g, b]);
+        }
+    }
+    // Send errors, such as the network going away when Wi-Fi drops, only cost frames: every
+    // universe is still tried, and the error is returned for the output thread to report.
+    fn show(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut error = None;
+        for (index, data) in self.pixels.chunks(PIXELS_PER_UNIVERSE * 3).enumerate() {
+            let universe = self.start_universe.wrapping_add(index as u16);
+            let sequence = self.sequences[index];
+            // Art-Net reserves 0 for "not sequenced".
+            self.sequences[index] = match (self.protocol, sequence.wrapping_add(1)) {
+                (Protocol::ArtNet, 0) => 1,
+                (_, next) => next,
+            };
+            let packet = self.packet(universe, sequence, data);
+            match self.socket.send_to(&packet, self.address(universe)) {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {},
+                Err(e) => error = Some(e),
+                Ok(_) => {},
+            }
+        }
+        match error {
+            Some(e) => Err(format!("Unable to send {:?}: {}", self.protocol, e).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+// An E1.31 data packet: root, framing and DMP layers, then the DMX start code and channels.
+fn e131_packet(cid: &[u8; 16], universe: u16, sequence: u8, data: &[u8]) -> Vec<u8> {
+    let length = 126 + data.len();
+    let flags_and_length = |from: usize| (0x7000 | (length - from) as u16).to_be_bytes();
+    let mut packet = Vec::with_capacity(length);
+    // Root layer.
+    packet.extend(&0x0010u16.to_be_bytes());  // Preamble size.
+    packet.extend(&0u16.to_be_bytes());  // Postamble size.
+    packet.extend(b"ASC-E1.17\0\0\0");
+    packet.extend(&flags_and_length(16));
+    packet.extend(&4u32.to_be_bytes());  // VECTOR_ROOT_E131_DATA.
+    packet.extend(cid);
+    // Framing layer.
+    packet.extend(&flags_and_length(38));
+    packet.extend(&2u32.to_be_bytes());  // VECTOR_E131_DATA_PACKET.
+    let mut name = [0u8; 64];
+    name[..SOURCE_NAME.len()].copy_from_slice(SOURCE_NAME.as_bytes());
+    packet.extend(&name[..]);
+    packet.push(100);  // Default priority.
+    packet.extend(&0u16.to_be_bytes());  // No synchronization universe.
+    packet.push(sequence);
+    packet.push(0);  // Options.
+    packet.extend(&universe.to_be_bytes());
+    // DMP layer.
+    packet.extend(&flags_and_length(115));
+    packet.push(2);  // VECTOR_DMP_SET_PROPERTY.
+    packet.push(0xa1);  // Address and data type.
+    packet.extend(&0u16.to_be_bytes());  // First property address.
+    packet.extend(&1u16.to_be_bytes());  // Address increment.
+    packet.extend(&(data.len() as u16 + 1).to_be_bytes());
+    packet.push(0);  // DMX start code.
+    packet.extend(data);
+    packet
+}
+
+// An ArtDmx packet. The universe is the 15 bit port address.
+fn artnet_packet(universe: u16, sequence: u8, data: &[u8]) -> Vec<u8> {
+    // The channel count must be even.
+    let length = data.len() + data.len() % 2;
+    let mut packet = Vec::with_capacity(18 + length);
+    packet.extend(b"Art-Net\0");
+    packet.extend(&0x5000u16.to_le_bytes());  // OpDmx.
+    packet.extend(&14u16.to_be_bytes());  // Protocol version.
+    packet.push(sequence);
+    packet.push(0);  // Physical port.
+    packet.extend(&(universe & 0x7FFF).to_le_bytes());  // SubUni, then Net.
+    packet.extend(&(length as u16).to_be_bytes());
+    packet.extend(data);
+    packet.resize(18 + length, 0);
+    packet
+}
@@ -0,0 +1,57 @@
+/**
+ * Sends each frame to several displays. Routes pick which pixels each display gets and where
+ * they land on it, so displays can mirror the whole frame or split it between them.
+ */
+use std::error::Error;
+
+use base::{FloatColor, Route};
+
+use crate::display::Display;
+
+pub struct FanOutDisplay {
+    children: Vec<(Box<dyn Display>, Vec<Route>)>,
+}
+
+impl FanOutDisplay {
+    pub fn new(children: Vec<(Box<dyn Display>, Vec<Route>)>) -> Self {
+        FanOutDisplay { children: children }
+    }
+}
+
+impl Display for FanOutDisplay {
+    fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8) {
+        for (display, routes) in self.children.iter_mut() {
+            for route in routes.iter() {
+                if index >= route.start && index < route.start + route.count {
+                    display.set_pixel(index - route.start + route.offset, r, g, b);
+                }
+            }
+        }
+    }
+    fn set_color(&mut self, index: usize, color: FloatColor) {
+        for (display, routes) in self.children.iter_mut() {
+            for route in routes.iter() {
+                if index >= route.start && index < route.start + route.count {
+                    display.set_color(index - route.start + route.offset, color);
+                }
+            }
+        }
+    }
+    fn set_brightness(&mut self, brightness: f32) {
+        for (display, _) in self.children.iter_mut() {
+            display.set_brightness(brightness);
+        }
+    }
+    // Shows every display even if one fails, then reports the first error.
+    fn show(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut result = Ok(());
+        for (display, _) in self.children.iter_mut() {
+            if let Err(e) = display.show() {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+}
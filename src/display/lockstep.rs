@@ -0,0 +1,62 @@
+/**
+ * Keeps threads in step like std's Barrier, except that the number of threads isn't fixed: each
+ * one joins to take part, and leaves when its Party is dropped. The rest then stop waiting for it,
+ * so a thread that exits early can't hold the others up for good.
+ */
+use std::sync::{Arc, Condvar, Mutex};
+
+#[derive(Default)]
+struct State {
+    parties: usize,
+    waiting: usize,
+    round: u64,  // Counts the times everyone has caught up.
+}
+
+impl State {
+    // Lets the waiting threads go if nobody else is still to come.
+    fn release(&mut self, caught_up: &Condvar) {
+        if self.waiting > 0 && self.waiting >= self.parties {
+            self.waiting = 0;
+            self.round += 1;
+            caught_up.notify_all();
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Lockstep {
+    state: Mutex<State>,
+    caught_up: Condvar,
+}
+
+pub struct Party {
+    lockstep: Arc<Lockstep>,
+}
+
+impl Lockstep {
+    pub fn join(lockstep: &Arc<Lockstep>) -> Party {
+        lockstep.state.lock().unwrap().parties += 1;
+        Party { lockstep: lockstep.clone() }
+    }
+}
+
+impl Party {
+    // Blocks until every other party is waiting too, or has left.
+    pub fn wait(&self) {
+        let mut state = self.lockstep.state.lock().unwrap();
+        let round = state.round;
+        state.waiting += 1;
+        state.release(&self.lockstep.caught_up);
+        while state.round == round {
+            state = self.lockstep.caught_up.wait(state).unwrap();
+        }
+    }
+}
+
+impl Drop for Party {
+    fn drop(&mut self) {
+        let mut state = self.lockstep.state.lock().unwrap();
+        state.parties -= 1;
+        state.release(&self.lockstep.caught_up);
+    }
+}
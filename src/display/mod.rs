@@ -1,19 +1,256 @@
 use std::error::Error;
+use std::sync::{Arc, Mutex};
 
-#[cfg_attr( target_arch = "arm", path = "blinkt_display.rs")]
-#[cfg_attr( not(target_arch = "arm"), path = "fake_display.rs")]
-pub mod display_impl;
+use base::{chain_length, ColorCorrection, DisplayConfig, DisplayKind, FloatColor, Layout, PowerConfig, PowerStatus};
+use base::Route;
+
+#[cfg(all(feature = "blinkt", target_arch = "arm"))]
+pub mod blinkt_display;
+pub mod correction;
+pub mod dither;
+pub mod dmx;
+pub mod fake_display;
+pub mod fan_out;
+pub mod lockstep;
+pub mod opc;
+pub mod power;
+pub mod recorder;
+#[cfg(target_os = "linux")]
+pub mod spi;
+pub mod terminal;
 
 /**
- * Provides a common interface for pattern output. We will only build one Display type.
- * Use new() to get the appropriate Display for the build configuration.
+ * Provides a common interface for pattern output. Backends are chosen at startup from the
+ * config; use from_configs() to make them.
  */
 pub trait Display {
     fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8);
+    // Sets a pixel to a color between the 8 bit values. Displays that can't use the extra
+    // precision round it.
+    fn set_color(&mut self, index: usize, color: FloatColor) {
+        let color = color.round();
+        self.set_pixel(index, color.r, color.g, color.b);
+    }
     fn show(&mut self) -> Result<(), Box<dyn Error>>;
-    fn set_offset(&mut self, count: usize);
+    // Scales the colors of the frames that follow, from 0 to 1. Only ColorCorrector acts on it.
+    fn set_brightness(&mut self, _brightness: f32) {}
+}
+
+// The display to use when none are configured, which depends on what was built in.
+pub fn default_config() -> DisplayConfig {
+    if cfg!(feature = "emulator") {
+        DisplayConfig::new(DisplayKind::Emulator)
+    } else if cfg!(all(feature = "blinkt", target_arch = "arm")) {
+        DisplayConfig::new(DisplayKind::Blinkt)
+    } else if cfg!(all(target_os = "linux", any(target_arch = "arm", target_arch = "aarch64"))) {
+        DisplayConfig::new(DisplayKind::Spi { bus: 0, chip_select: 0, clock_hz: 8_000_000, device: None })
+    } else {
+        DisplayConfig::new(DisplayKind::Null)
+    }
+}
+
+#[cfg(all(feature = "blinkt", target_arch = "arm"))]
+fn make_blinkt(pixels: usize) -> Result<Box<dyn Display>, Box<dyn Error>> {
+    Ok(Box::new(blinkt_display::make_display(pixels)?))
+}
+
+#[cfg(not(all(feature = "blinkt", target_arch = "arm")))]
+fn make_blinkt(_pixels: usize) -> Result<Box<dyn Display>, Box<dyn Error>> {
+    Err("wavesuit was built without the blinkt feature, which needs a Raspberry Pi".into())
+}
+
+#[cfg(target_os = "linux")]
+fn make_spi(device: &str, clock_hz: u32, layout: &Layout, routes: Vec<Route>, chains: &Arc<lockstep::Lockstep>)
+            -> Result<Box<dyn Display>, Box<dyn Error>> {
+    Ok(Box::new(spi::SpiDisplay::new(device, clock_hz, layout, routes, chains)?))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn make_spi(_device: &str, _clock_hz: u32, _layout: &Layout, _routes: Vec<Route>, _chains: &Arc<lockstep::Lockstep>)
+            -> Result<Box<dyn Display>, Box<dyn Error>> {
+    Err("SPI displays need Linux".into())
+}
+
+#[cfg(feature = "emulator")]
+fn make_emulator(layout: &Layout) -> Result<Box<dyn Display>, Box<dyn Error>> {
+    crate::runner::emulator::make_display(layout)
+}
+
+#[cfg(not(feature = "emulator"))]
+fn make_emulator(_layout: &Layout) -> Result<Box<dyn Display>, Box<dyn Error>> {
+    Err("wavesuit was built without the emulator feature".into())
+}
+
+// Sizes of the parts of a frame: the suit's LEDs, then the belt's.
+struct FrameSizes {
+    suit: usize,
+    belt: usize,
 }
 
-pub fn new(pixels: usize) -> Result<Box<dyn Display>, Box<dyn Error>> {
-    return Ok(Box::new( display_impl::make_display(pixels)? ));
+impl FrameSizes {
+    fn new(layout: &Layout) -> Self {
+        FrameSizes { suit: chain_length(&layout.active_segments(false)),
+                     belt: chain_length(&layout.active_segments(true)) }
+    }
+    fn all(&self) -> usize { self.suit + self.belt }
+}
+
+fn default_routes(kind: &DisplayKind, sizes: &FrameSizes) -> Vec<Route> {
+    match kind {
+        // The LED chain holds whichever garment is plugged in.
+        kind if kind.is_led_chain() => vec![
+            Route { start: 0, count: sizes.suit, offset: 0 },
+            Route { start: sizes.suit, count: sizes.belt, offset: 0 },
+        ],
+        _ => vec![Route { start: 0, count: sizes.all(), offset: 0 }],
+    }
+}
+
+// The number of display pixels the routes fill.
+fn routed_pixels(routes: &[Route]) -> usize {
+    routes.iter().map(|route| route.offset + route.count).max().unwrap_or(0)
+}
+
+// Routes that lay the segments end to end along a display, with the belt's starting again at the
+// first LED.
+fn segment_routes(layout: &Layout, names: &[String]) -> Result<Vec<Route>, Box<dyn Error>> {
+    let segments = layout.display_segments();
+    let mut routes = Vec::with_capacity(names.len());
+    let mut offsets = [0, 0];  // Next display LED for the suit and the belt.
+    for name in names.iter() {
+        let start = match segments.iter().position(|segment| &segment.name == name) {
+            Some(start) => start,
+            None => return Err(format!("No segment {} to display", name).into()),
+        };
+        let segment = segments[start];
+        let count = segment.chain().len();
+        let offset = &mut offsets[segment.belt as usize];
+        routes.push(Route { start: start, count: count, offset: *offset });
+        *offset += count;
+    }
+    Ok(routes)
+}
+
+// chains is shared by every SPI display, to show them together.
+fn new(kind: &DisplayKind, layout: &Layout, routes: &[Route], chains: &Arc<lockstep::Lockstep>)
+       -> Result<Box<dyn Display>, Box<dyn Error>> {
+    let pixels = routed_pixels(routes);
+    match kind {
+        DisplayKind::Null => Ok(Box::new(fake_display::make_display(pixels)?)),
+        DisplayKind::Blinkt => make_blinkt(pixels),
+        DisplayKind::Spi { bus, chip_select, clock_hz, device } => {
+            let device = device.clone().unwrap_or(format!("/dev/spidev{}.{}", bus, chip_select));
+            make_spi(&device, *clock_hz, layout, routes.to_vec(), chains)
+        },
+        DisplayKind::Emulator => make_emulator(layout),
+        DisplayKind::Terminal => Ok(Box::new(terminal::TerminalDisplay::new(layout))),
+        DisplayKind::Opc { address, channel } => {
+            Ok(Box::new(opc::OpcDisplay::new(address, *channel, pixels)?))
+        },
+        DisplayKind::E131 { destination, start_universe } => {
+            let destination = dmx::parse_destination(dmx::Protocol::E131, destination)?;
+            Ok(Box::new(dmx::DmxDisplay::new(dmx::Protocol::E131, destination, *start_universe, pixels)?))
+        },
+        DisplayKind::ArtNet { destination, start_universe } => {
+            let destination = dmx::parse_destination(dmx::Protocol::ArtNet, destination)?;
+            Ok(Box::new(dmx::DmxDisplay::new(dmx::Protocol::ArtNet, destination, *start_universe, pixels)?))
+        },
+        DisplayKind::Record { path } => Ok(Box::new(recorder::RecordingDisplay::create(path, pixels)?)),
+    }
+}
+
+// Makes every configured display. Frames sent to the result hold the suit's LEDs followed by the
+// belt's, and each display gets the pixels its routes ask for. The LED chains share the power
+// budget; the other displays aren't on the battery, so they are never dimmed for it. Displays
+// that want it get color, and the rest get only the brightness.
+pub fn from_configs(configs: &[DisplayConfig], layout: &Layout, color: &ColorCorrection, power: PowerConfig,
+                    power_status: Arc<Mutex<PowerStatus>>) -> Result<Box<dyn Display>, Box<dyn Error>> {
+    let correction = |corrected: bool| if corrected {color.clone()} else {ColorCorrection::identity()};
+    let chains_corrected = configs.iter().filter(|config| config.kind.is_led_chain())
+        .map(|config| config.corrected()).collect::<Vec<bool>>();
+    if chains_corrected.iter().any(|&corrected| corrected != chains_corrected[0]) {
+        return Err("The LED chains share their color correction, so set correct the same for all of them".into());
+    }
+    let sizes = FrameSizes::new(layout);
+    let chains = Arc::new(lockstep::Lockstep::default());
+    let mut children: Vec<(Box<dyn Display>, Vec<Route>)> = Vec::with_capacity(configs.len());
+    let mut led_chains = Vec::new();
+    for config in configs.iter() {
+        let routes = if config.routes.len() > 0 {
+            config.routes.clone()
+        } else if config.segments.len() > 0 {
+            segment_routes(layout, &config.segments)?
+        } else {
+            default_routes(&config.kind, &sizes)
+        };
+        let display = new(&config.kind, layout, &routes, &chains)?;
+        // Blinkt and SPI dither around their own brightness steps; everything else is dithered to 8 bits.
+        match config.kind {
+            // It needs to know where each pixel came from, so it does its own routing.
+            DisplayKind::Spi { .. } => {
+                led_chains.push((display, vec![Route { start: 0, count: sizes.all(), offset: 0 }]));
+            },
+            DisplayKind::Blinkt => led_chains.push((display, routes)),
+            _ => {
+                let pixels = routed_pixels(&routes);
+                let display: Box<dyn Display> = Box::new(dither::DitheringDisplay::new(display, pixels));
+                if config.kind.is_led_chain() {
+                    led_chains.push((display, routes));
+                } else {
+                    // Corrected before routing, since the segment calibrations go by frame LED.
+                    let routed = fan_out::FanOutDisplay::new(vec![(display, routes)]);
+                    let corrector = correction::ColorCorrector::new(Box::new(routed), layout,
+                                                                    &correction(config.corrected()));
+                    children.push((Box::new(corrector), vec![Route { start: 0, count: sizes.all(), offset: 0 }]));
+                }
+            },
+        }
+    }
+    if led_chains.len() > 0 {
+        let led_chains = fan_out::FanOutDisplay::new(led_chains);
+        let limiter = power::PowerLimiter::new(Box::new(led_chains), sizes.all(), power, power_status);
+        // Correct colors before the power limiter sees them, since those are what the LEDs draw.
+        let corrector = correction::ColorCorrector::new(Box::new(limiter), layout, &correction(chains_corrected[0]));
+        children.push((Box::new(corrector), vec![Route { start: 0, count: sizes.all(), offset: 0 }]));
+    }
+    Ok(Box::new(fan_out::FanOutDisplay::new(children)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use base::{CalibrationOverrides, Color};
+
+    use super::*;
+
+    #[test]
+    fn routed_displays_get_their_segments_calibration() {
+        let segment = |name: &str, x: f64| format!(
+            r#"{{"name": "{}", "region": "back", "width": 1, "height": 2, "strip_step": [0, 1],
+                "column_step": [1, 0], "panels": [{{"strips": 1, "origin": [{}, 0]}}]}}"#, name, x);
+        let layout = Layout::deserialize(&format!(r#"{{"scale": 1, "segments": [{}, {}]}}"#,
+                                                  segment("left", 0.0), segment("right", 1.0))).unwrap();
+        let mut segments = BTreeMap::new();
+        segments.insert(String::from("right"), CalibrationOverrides { gamma: None, gains: Some([0.0, 1.0, 1.0]) });
+        let color = ColorCorrection { segments: segments, ..ColorCorrection::identity() };
+        let path = std::env::temp_dir().join(format!("wavesuit-routed-{}", std::process::id()));
+        let mut config = DisplayConfig::new(DisplayKind::Record { path: path.to_str().unwrap().to_string() });
+        config.segments = vec![String::from("right")];
+        config.correct = Some(true);
+
+        {
+            let mut display = from_configs(&[config], &layout, &color, PowerConfig::default(),
+                                           Arc::new(Mutex::new(PowerStatus::default()))).unwrap();
+            for led in 0..4 {
+                display.set_pixel(led, 255, 255, 255);
+            }
+            display.show().unwrap();
+        }
+
+        let mut reader = recorder::RecordingReader::open(path.to_str().unwrap()).unwrap();
+        let frame = reader.next_frame().unwrap().unwrap();
+        assert_eq!(frame.pixels, vec![Color{r: 0, g: 255, b: 255}; 2]);
+        let _ = std::fs::remove_file(path);
+    }
 }
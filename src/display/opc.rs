@@ -0,0 +1,195 @@
+/**
+ * Streams frames to an Open Pixel Control server, such as fcserver for Fadecandy boards or the
+ * OPC GL simulator. See http://openpixelcontrol.org.
+ *
+ * Each frame is one "set pixel colors" message: channel, command 0, big endian data length, then
+ * RGB for every pixel. A background thread owns the connection, so show() never waits on the
+ * network. Frames are dropped while the server is unreachable, and the thread keeps trying to
+ * reconnect with a growing delay. show() returns why, so it is reported with the other displays'
+ * errors.
+ */
+use std::error::Error;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{bounded, Receiver, Sender};
+
+use crate::display::Display;
+
+const SET_PIXEL_COLORS: u8 = 0;
+const MAX_DATA_LENGTH: usize = 0xFFFF;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+const MIN_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+pub struct OpcDisplay {
+    message: Vec<u8>,  // Header followed by the pixels.
+    sender: Sender<Vec<u8>>,
+    unreachable: Arc<Mutex<Option<String>>>,  // Why the server can't be reached, if it can't.
+}
+
+impl OpcDisplay {
+    // address is host:port. Channel 0 sends to every channel on the server.
+    pub fn new(address: &str, channel: u8, pixels: usize) -> Result<Self, Box<dyn Error>> {
+        let length = pixels * 3;
+        if length > MAX_DATA_LENGTH {
+            return Err(format!("{} pixels don't fit in one OPC message", pixels).into());
+        }
+        let mut message = vec![0; 4 + length];
+        message[0] = channel;
+        message[1] = SET_PIXEL_COLORS;
+        message[2..4].copy_from_slice(&(length as u16).to_be_bytes());
+
+        // Only the newest frame is worth sending, so don't queue more than one.
+        let (sender, receiver) = bounded(1);
+        println!("Sending frames to OPC server {} on channel {}", address, channel);
+        let address = address.to_string();
+        let unreachable = Arc::new(Mutex::new(None));
+        let thread_unreachable = unreachable.clone();
+        thread::spawn(move || send_frames(&address, receiver, thread_unreachable));
+        Ok(OpcDisplay { message: message, sender: sender, unreachable: unreachable })
+    }
+}
+
+impl Display for OpcDisplay {
+    fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8) {
+        let at = 4 + index * 3;
+        if at + 3 <= self.message.len() {
+            self.message[at..at + 3].copy_from_slice(&[r, g, b]);
+        }
+    }
+    fn show(&mut self) -> Result<(), Box<dyn Error>> {
+        // Drops the frame if the last one hasn't gone out yet.
+        let _ = self.sender.try_send(self.message.clone());
+        match self.unreachable.lock().unwrap().as_ref() {
+            Some(reason) => Err(reason.clone().into()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn connect(address: &str) -> Result<TcpStream, Box<dyn Error>> {
+    let mut last_error: Box<dyn Error> = format!("No addresses for {}", address).into();
+    for socket_address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&socket_address, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_nodelay(true)?;
+                // A server that stops reading counts as lost rather than stalling the thread.
+                stream.set_write_timeout(Some(CONNECT_TIMEOUT))?;
+                return Ok(stream);
+            },
+            Err(e) => last_error = Box::new(e),
+        }
+    }
+    Err(last_error)
+}
+
+// Runs on its own thread until the display is dropped.
+fn send_frames(address: &str, frames: Receiver<Vec<u8>>, unreachable: Arc<Mutex<Option<String>>>) {
+    let mut stream: Option<TcpStream> = None;
+    let mut backoff = MIN_BACKOFF;
+    let mut next_attempt = Instant::now();
+    for frame in frames.iter() {
+        if stream.is_none() {
+            if Instant::now() < next_attempt {
+                continue;
+            }
+            match connect(address) {
+                Ok(connected) => {
+                    *unreachable.lock().unwrap() = None;
+                    stream = Some(connected);
+                    backoff = MIN_BACKOFF;
+                },
+                Err(e) => {
+                    let reason = format!("Unable to connect to OPC server {}: {}", address, e);
+                    *unreachable.lock().unwrap() = Some(reason);
+                    next_attempt = Instant::now() + backoff;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    continue;
+                },
+            }
+        }
+        if let Err(e) = stream.as_mut().unwrap().write_all(&frame) {
+            *unreachable.lock().unwrap() = Some(format!("Lost OPC server {}: {}", address, e));
+            stream = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{ErrorKind, Read};
+    use std::net::{SocketAddr, TcpListener};
+
+    use super::*;
+
+    // Shows frames until the display connects, since it only tries when it has one to send.
+    fn accept(listener: &TcpListener, display: &mut OpcDisplay) -> TcpStream {
+        listener.set_nonblocking(true).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            // Fails until it has reconnected.
+            let _ = display.show();
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false).unwrap();
+                    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+                    return stream;
+                },
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(10)),
+                Err(e) => panic!("{}", e),
+            }
+        }
+        panic!("The display never connected");
+    }
+
+    fn read_message(stream: &mut TcpStream) -> (u8, u8, Vec<u8>) {
+        let mut header = [0; 4];
+        stream.read_exact(&mut header).unwrap();
+        let mut data = vec![0; u16::from_be_bytes([header[2], header[3]]) as usize];
+        stream.read_exact(&mut data).unwrap();
+        (header[0], header[1], data)
+    }
+
+    fn start() -> (TcpListener, SocketAddr, OpcDisplay) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut display = OpcDisplay::new(&address.to_string(), 2, 3).unwrap();
+        display.set_pixel(0, 1, 2, 3);
+        display.set_pixel(2, 7, 8, 9);
+        display.set_pixel(3, 255, 255, 255);  // Past the end, so ignored.
+        (listener, address, display)
+    }
+
+    #[test]
+    fn sends_set_pixel_colors() {
+        let (listener, _, mut display) = start();
+        let mut stream = accept(&listener, &mut display);
+        let (channel, command, data) = read_message(&mut stream);
+        assert_eq!((channel, command), (2, SET_PIXEL_COLORS));
+        assert_eq!(data, vec![1, 2, 3, 0, 0, 0, 7, 8, 9]);
+    }
+
+    #[test]
+    fn reconnects_after_losing_the_server() {
+        let (listener, address, mut display) = start();
+        let mut stream = accept(&listener, &mut display);
+        read_message(&mut stream);
+        drop(stream);
+        drop(listener);
+
+        let listener = TcpListener::bind(address).unwrap();
+        display.set_pixel(1, 4, 5, 6);
+        let mut stream = accept(&listener, &mut display);
+        // A frame from before the change may still have been waiting to go out.
+        let (_, _, mut data) = read_message(&mut stream);
+        if data != vec![1, 2, 3, 4, 5, 6, 7, 8, 9] {
+            let _ = display.show();
+            data = read_message(&mut stream).2;
+        }
+        assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+}
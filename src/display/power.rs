@@ -0,0 +1,118 @@
+/**
+ * Estimates the current each frame will draw and dims frames that would draw more than the
+ * budget, so bright patterns can't brown out the battery or overheat the wiring.
+ *
+ * Each channel draws in proportion to its value, up to the configured current at 255, and every
+ * LED draws a little even when dark. Only the pixels set since the last frame are counted and
+ * passed on, since the suit and the belt take turns on the same chain. When a frame is over
+ * budget it is scaled down straight away; once frames fit again the brightness comes back over
+ * a couple of seconds instead of jumping.
+ */
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+use base::{Color, FloatColor, PowerConfig, PowerStatus};
+
+use crate::display::Display;
+
+// How much of the way back to full brightness each frame goes.
+const RECOVERY: f32 = 0.05;
+
+pub struct PowerLimiter {
+    display: Box<dyn Display>,
+    config: PowerConfig,
+    pixels: Vec<FloatColor>,
+    set: Vec<usize>,  // Pixels set this frame, in order.
+    scale: f32,
+    limiting: bool,
+    status: Arc<Mutex<PowerStatus>>,
+}
+
+impl PowerLimiter {
+    pub fn new(display: Box<dyn Display>, pixels: usize, config: PowerConfig,
+               status: Arc<Mutex<PowerStatus>>) -> Self {
+        if let Some(budget) = config.budget_ma {
+            println!("Limiting the LEDs to {} mA", budget);
+        }
+        PowerLimiter { display: display, config: config, pixels: vec![FloatColor::black(); pixels],
+                       set: Vec::new(), scale: 1.0, limiting: false, status: status }
+    }
+
+    // Estimated current for this frame's pixels at the given brightness.
+    fn estimate(&self, scale: f32) -> f32 {
+        let total: f32 = self.set.iter()
+            .map(|&index| self.pixels[index])
+            .map(|c| c.r.max(0.0).min(255.0) + c.g.max(0.0).min(255.0) + c.b.max(0.0).min(255.0))
+            .sum();
+        let idle = self.set.len() as f32 * self.config.idle_ma;
+        idle + total / 255.0 * self.config.channel_ma * scale
+    }
+
+    // The brightness that brings the frame within the budget, or 1.0 if it already fits.
+    fn target_scale(&self, estimate: f32) -> f32 {
+        let budget = match self.config.budget_ma {
+            Some(budget) => budget,
+            None => return 1.0,
+        };
+        let idle = self.set.len() as f32 * self.config.idle_ma;
+        if estimate <= budget || estimate <= idle {
+            return 1.0;
+        }
+        ((budget - idle) / (estimate - idle)).max(0.0)
+    }
+}
+
+impl Display for PowerLimiter {
+    fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8) {
+        self.set_color(index, FloatColor::from(Color{r: r, g: g, b: b}));
+    }
+    fn set_color(&mut self, index: usize, color: FloatColor) {
+        if index < self.pixels.len() {
+            self.pixels[index] = color;
+            self.set.push(index);
+        }
+    }
+    fn show(&mut self) -> Result<(), Box<dyn Error>> {
+        self.set.sort();
+        self.set.dedup();
+        let estimate = self.estimate(1.0);
+        let target = self.target_scale(estimate);
+        self.scale = if target < self.scale {
+            target
+        } else {
+            self.scale + (target - self.scale) * RECOVERY
+        };
+        // Close enough to stop scaling.
+        if self.scale > 0.999 {
+            self.scale = 1.0;
+        }
+        let limited = target < 1.0;
+        // A limiting event lasts until the brightness is all the way back, so a pattern hovering
+        // around the budget only starts one.
+        let starting = limited && !self.limiting;
+        if starting {
+            println!("Power limiting: {:.0} mA is over the {:.0} mA budget", estimate,
+                     self.config.budget_ma.unwrap_or(0.0));
+        }
+
+        for &index in self.set.iter() {
+            self.display.set_color(index, self.pixels[index] * self.scale);
+        }
+        {
+            let mut status = self.status.lock().unwrap();
+            status.budget_ma = self.config.budget_ma;
+            status.estimated_ma = estimate;
+            status.output_ma = self.estimate(self.scale);
+            status.scale = self.scale;
+            if limited {
+                status.limited_frames += 1;
+            }
+            if starting {
+                status.limit_events += 1;
+            }
+        }
+        self.limiting = self.scale < 1.0;
+        self.set.clear();
+        self.display.show()
+    }
+}
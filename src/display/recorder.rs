@@ -0,0 +1,160 @@
+/**
+ * A Display that writes every frame to a file, so shows can be inspected or replayed later.
+ * Add it alongside the other displays to record what they show.
+ *
+ * File format, little endian:
+ *   "WSREC" magic, u8 version (1), u32 LED count
+ *   then for each frame: u64 microseconds since recording started, LED count * [r, g, b]
+ */
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::time::Instant;
+
+use base::Color;
+
+use crate::display::Display;
+
+const MAGIC: &[u8; 5] = b"WSREC";
+const VERSION: u8 = 1;
+
+pub struct RecordingDisplay {
+    writer: BufWriter<File>,
+    frame: Vec<u8>,
+    start: Instant,
+}
+
+impl RecordingDisplay {
+    pub fn create(path: &str, pixels: usize) -> Result<Self, Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&(pixels as u32).to_le_bytes())?;
+        println!("Recording frames to {}", path);
+        Ok(RecordingDisplay { writer: writer, frame: vec![0; pixels * 3], start: Instant::now() })
+    }
+}
+
+impl Display for RecordingDisplay {
+    fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8) {
+        if index * 3 < self.frame.len() {
+            self.frame[index * 3..index * 3 + 3].copy_from_slice(&[r, g, b]);
+        }
+    }
+    fn show(&mut self) -> Result<(), Box<dyn Error>> {
+        let micros = self.start.elapsed().as_micros() as u64;
+        self.writer.write_all(&micros.to_le_bytes())?;
+        self.writer.write_all(&self.frame)?;
+        // Power can go at any time in the field, so don't leave frames sitting in the buffer.
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// One frame read back from a recording.
+pub struct RecordedFrame {
+    pub micros: u64,
+    pub pixels: Vec<Color>,
+}
+
+// Reads the frames of a recording in order.
+pub struct RecordingReader<R: Read> {
+    reader: R,
+    pixels: usize,
+}
+
+impl RecordingReader<BufReader<File>> {
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        RecordingReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> RecordingReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut header = [0; 10];
+        reader.read_exact(&mut header)?;
+        if &header[0..5] != MAGIC || header[5] != VERSION {
+            return Err("Not a wavesuit recording".into());
+        }
+        let pixels = u32::from_le_bytes([header[6], header[7], header[8], header[9]]) as usize;
+        Ok(RecordingReader { reader: reader, pixels: pixels })
+    }
+
+    pub fn pixels(&self) -> usize { self.pixels }
+
+    // The next frame, or None at the end of the recording. A frame cut short, as when the power
+    // goes mid-write, ends the recording too.
+    pub fn next_frame(&mut self) -> io::Result<Option<RecordedFrame>> {
+        let mut micros = [0; 8];
+        let mut rgb = vec![0; self.pixels * 3];
+        match self.reader.read_exact(&mut micros).and_then(|_| self.reader.read_exact(&mut rgb)) {
+            Ok(()) => {},
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let pixels = rgb.chunks(3).map(|c| Color{r: c[0], g: c[1], b: c[2]}).collect();
+        Ok(Some(RecordedFrame { micros: u64::from_le_bytes(micros), pixels: pixels }))
+    }
+}
+
+// Plays a recording back in real time, holding the last frame once it ends.
+pub struct Replay {
+    reader: RecordingReader<BufReader<File>>,
+    start: Instant,
+    next: Option<RecordedFrame>,
+    current: Vec<Color>,
+}
+
+impl Replay {
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut reader = RecordingReader::open(path)?;
+        let next = reader.next_frame()?;
+        let current = vec![Color::new(0); reader.pixels()];
+        Ok(Replay { reader: reader, start: Instant::now(), next: next, current: current })
+    }
+
+    // The latest frame due at the current time.
+    pub fn frame(&mut self) -> Result<&[Color], Box<dyn Error>> {
+        let now = self.start.elapsed().as_micros() as u64;
+        while self.next.as_ref().map_or(false, |frame| frame.micros <= now) {
+            self.current = self.next.take().unwrap().pixels;
+            self.next = self.reader.next_frame()?;
+        }
+        Ok(&self.current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+
+    use super::*;
+
+    #[test]
+    fn truncated_recording_ends_at_last_whole_frame() {
+        let path = std::env::temp_dir().join(format!("wavesuit-recording-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut display = RecordingDisplay::create(path, 4).unwrap();
+        for frame in 0..5u8 {
+            for led in 0..4 {
+                display.set_pixel(led, frame, led as u8, 255 - frame);
+            }
+            display.show().unwrap();
+        }
+        drop(display);
+        let length = fs::metadata(path).unwrap().len();
+        OpenOptions::new().write(true).open(path).unwrap().set_len(length - 5).unwrap();
+
+        let mut reader = RecordingReader::open(path).unwrap();
+        assert_eq!(reader.pixels(), 4);
+        for frame in 0..4u8 {
+            let pixels = reader.next_frame().unwrap().unwrap().pixels;
+            assert_eq!(pixels.len(), 4);
+            for (led, pixel) in pixels.iter().enumerate() {
+                assert_eq!((pixel.r, pixel.g, pixel.b), (frame, led as u8, 255 - frame));
+            }
+        }
+        assert!(reader.next_frame().unwrap().is_none());
+        fs::remove_file(path).unwrap();
+    }
+}
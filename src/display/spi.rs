@@ -0,0 +1,233 @@
+/**
+ * Drives a chain of APA102 LEDs straight from Linux's spidev, on any Pi. Colors keep their
+ * precision down to the encoder, which spreads them over the LEDs' brightness field, and what
+ * is left over is dithered.
+ *
+ * The display does its own routing instead of leaving it to FanOutDisplay: the suit and the belt
+ * share the start of the chain, and each LED has to go out in the color order of the segment it
+ * came from.
+ *
+ * Long garments can be split over several chains on separate buses to keep the frame rate up.
+ * Each chain is written from its own thread, and the chains share a Lockstep so every chain
+ * starts each frame together. show() only waits for the chain's last frame to finish.
+ */
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::sync::Arc;
+use std::thread;
+
+use base::apa102::{self, Apa102Pixel, ColorOrder};
+use base::{Color, Dither, FloatColor, Layout, Route};
+use crossbeam_channel::{bounded, Receiver, Sender};
+use spidev::{SpiModeFlags, Spidev, SpidevOptions};
+
+use crate::display::{routed_pixels, Display};
+use crate::display::lockstep::{Lockstep, Party};
+
+// spidev's default limit on one transfer.
+const MAX_TRANSFER: usize = 4096;
+
+pub struct SpiDisplay {
+    routes: Vec<Route>,
+    orders: Vec<ColorOrder>,  // For each frame LED.
+    pixels: Vec<(Apa102Pixel, ColorOrder)>,
+    dither: Dither,  // By chain LED.
+    frames: Sender<Vec<u8>>,
+    // Frames back from the writer once they are sent, to reuse.
+    written: Receiver<(Vec<u8>, io::Result<()>)>,
+}
+
+fn open(device: &str, clock_hz: u32) -> Result<Spidev, Box<dyn Error>> {
+    // Checked first, so a missing device is never created as a file.
+    let metadata = fs::metadata(device).map_err(|e| format!("Unable to open {}: {}", device, e))?;
+    if !metadata.file_type().is_char_device() {
+        return Err(format!("{} isn't a SPI device", device).into());
+    }
+    let mut spi = Spidev::open(device).map_err(|e| format!("Unable to open {}: {}", device, e))?;
+    let options = SpidevOptions::new()
+        .bits_per_word(8)
+        .max_speed_hz(clock_hz)
+        .mode(SpiModeFlags::SPI_MODE_0)
+        .build();
+    spi.configure(&options)?;
+    Ok(spi)
+}
+
+impl SpiDisplay {
+    // The routes are from frame pixels to LEDs on the chain. Every chain shown together shares
+    // the lockstep.
+    pub fn new(device: &str, clock_hz: u32, layout: &Layout, routes: Vec<Route>, chains: &Arc<Lockstep>)
+               -> Result<Self, Box<dyn Error>> {
+        let spi = open(device, clock_hz)?;
+        println!("Sending {} LEDs to {} at {} Hz", routed_pixels(&routes), device, clock_hz);
+        SpiDisplay::with_writer(Box::new(spi), layout, routes, chains)
+    }
+
+    // Sends the frames to any writer instead of a SPI device, e.g. a file to see what a chain
+    // would get.
+    pub fn with_writer(writer: Box<dyn Write + Send>, layout: &Layout, routes: Vec<Route>, chains: &Arc<Lockstep>)
+                       -> Result<Self, Box<dyn Error>> {
+        let pixels = routed_pixels(&routes);
+        let (frames, frame_receiver) = bounded(1);
+        let (written_sender, written) = bounded(1);
+        // Nothing to wait for before the first frame.
+        written_sender.send((Vec::new(), Ok(())))?;
+        // Joined here rather than on the thread, so every chain is counted before the first frame.
+        let party = Lockstep::join(chains);
+        thread::spawn(move || write_frames(writer, party, frame_receiver, written_sender));
+        Ok(SpiDisplay { routes: routes,
+                        orders: layout.display_segments().iter().map(|segment| segment.color_order).collect(),
+                        pixels: vec![Default::default(); pixels], dither: Dither::new(pixels),
+                        frames: frames, written: written })
+    }
+}
+
+impl Display for SpiDisplay {
+    fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8) {
+        self.set_color(index, FloatColor::from(Color{r: r, g: g, b: b}));
+    }
+    fn set_color(&mut self, index: usize, color: FloatColor) {
+        let order = self.orders.get(index).cloned().unwrap_or_default();
+        for route in self.routes.iter() {
+            if index >= route.start && index < route.start + route.count {
+                let led = index - route.start + route.offset;
+                self.pixels[led] = (Apa102Pixel::dithered(&mut self.dither, led, color), order);
+            }
+        }
+    }
+    // Reports errors from writing the last frame. Sends this one regardless, so the other chains
+    // aren't left waiting for it.
+    fn show(&mut self) -> Result<(), Box<dyn Error>> {
+        let (mut frame, result) = self.written.recv()?;
+        apa102::encode(&self.pixels, &mut frame);
+        self.frames.send(frame)?;
+        Ok(result?)
+    }
+}
+
+// Runs on its own thread until the display is dropped. The chain leaves the lockstep when this
+// returns, so the others carry on without it.
+fn write_frames(mut writer: Box<dyn Write + Send>, chains: Party, frames: Receiver<Vec<u8>>,
+                written: Sender<(Vec<u8>, io::Result<()>)>) {
+    for frame in frames.iter() {
+        chains.wait();
+        // The LEDs latch on the clock rather than chip select, so splitting the frame is harmless.
+        let result = frame.chunks(MAX_TRANSFER).map(|chunk| writer.write_all(chunk)).collect();
+        if written.send((frame, result)).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("wavesuit-spi-{}-{}", std::process::id(), name))
+    }
+
+    // Two segments of two LEDs each, taking their channels in different orders.
+    fn layout() -> Layout {
+        let segment = |name: &str, order: &str, x: f64| format!(
+            r#"{{"name": "{}", "region": "back", "width": 1, "height": 2, "strip_step": [0, 1],
+                "column_step": [1, 0], "panels": [{{"strips": 1, "origin": [{}, 0]}}],
+                "color_order": "{}"}}"#, name, x, order);
+        Layout::deserialize(&format!(r#"{{"scale": 1, "segments": [{}, {}]}}"#,
+                                     segment("left", "rgb", 0.0), segment("right", "grb", 1.0))).unwrap()
+    }
+
+    fn chain(name: &str, routes: Vec<Route>, chains: &Arc<Lockstep>) -> (PathBuf, SpiDisplay) {
+        let path = temp_path(name);
+        let file = File::create(&path).unwrap();
+        (path.clone(), SpiDisplay::with_writer(Box::new(file), &layout(), routes, chains).unwrap())
+    }
+
+    // Waits for the writer thread to get length bytes into the file.
+    fn written(path: &PathBuf, length: usize) -> Vec<u8> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            let bytes = fs::read(path).unwrap();
+            if bytes.len() >= length {
+                return bytes;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("{} never got {} bytes", path.display(), length);
+    }
+
+    // The bytes for a frame of full brightness LEDs.
+    fn frame(channels: &[[u8; 3]]) -> Vec<u8> {
+        let mut bytes = vec![0; 4];
+        for led in channels.iter() {
+            bytes.push(0xFF);
+            bytes.extend_from_slice(led);
+        }
+        bytes.resize(bytes.len() + apa102::end_frame_length(channels.len()), 0);
+        bytes
+    }
+
+    fn paint(displays: &mut [&mut SpiDisplay]) {
+        let colors = [(255, 1, 2), (3, 255, 4), (5, 6, 255), (255, 7, 8)];
+        for display in displays.iter_mut() {
+            for (index, &(r, g, b)) in colors.iter().enumerate() {
+                display.set_pixel(index, r, g, b);
+            }
+            display.show().unwrap();
+        }
+    }
+
+    #[test]
+    fn sends_each_chain_its_segments_in_their_color_order() {
+        let chains = Arc::new(Lockstep::default());
+        let (left_path, mut left) = chain("left", vec![Route { start: 0, count: 2, offset: 0 }], &chains);
+        // The right segment twice over, reversed into the second half of the chain first.
+        let (both_path, mut both) = chain("both", vec![Route { start: 2, count: 2, offset: 2 },
+                                                       Route { start: 0, count: 2, offset: 0 }], &chains);
+        paint(&mut [&mut left, &mut both]);
+
+        let left_frame = frame(&[[255, 1, 2], [3, 255, 4]]);
+        let both_frame = frame(&[[255, 1, 2], [3, 255, 4], [6, 5, 255], [7, 255, 8]]);
+        assert_eq!(written(&left_path, left_frame.len()), left_frame);
+        assert_eq!(written(&both_path, both_frame.len()), both_frame);
+        let _ = fs::remove_file(left_path);
+        let _ = fs::remove_file(both_path);
+    }
+
+    #[test]
+    fn keeps_sending_when_another_chain_stops() {
+        let chains = Arc::new(Lockstep::default());
+        let (left_path, mut left) = chain("kept", vec![Route { start: 0, count: 2, offset: 0 }], &chains);
+        let (right_path, mut right) = chain("stopped", vec![Route { start: 2, count: 2, offset: 0 }], &chains);
+        paint(&mut [&mut left, &mut right]);
+        let right_frame = frame(&[[6, 5, 255], [7, 255, 8]]);
+        assert_eq!(written(&right_path, right_frame.len()), right_frame);
+
+        drop(right);
+        paint(&mut [&mut left]);
+        paint(&mut [&mut left]);
+        let left_frame = frame(&[[255, 1, 2], [3, 255, 4]]);
+        assert_eq!(written(&left_path, 3 * left_frame.len()), left_frame.repeat(3));
+        let _ = fs::remove_file(left_path);
+        let _ = fs::remove_file(right_path);
+    }
+
+    #[test]
+    fn only_opens_spi_devices() {
+        let chains = Arc::new(Lockstep::default());
+        let missing = temp_path("missing");
+        assert!(SpiDisplay::new(missing.to_str().unwrap(), 8_000_000, &layout(), Vec::new(), &chains).is_err());
+        assert!(!missing.exists());
+
+        let file = temp_path("file");
+        File::create(&file).unwrap();
+        assert!(SpiDisplay::new(file.to_str().unwrap(), 8_000_000, &layout(), Vec::new(), &chains).is_err());
+        let _ = fs::remove_file(file);
+    }
+}
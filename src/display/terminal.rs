@@ -0,0 +1,100 @@
+/**
+ * Draws the LEDs in the terminal at their layout positions, for watching the painters over SSH.
+ * Each character cell holds two LEDs using the upper half block, colored with 24-bit ANSI
+ * escapes: the foreground is the top LED and the background the bottom one.
+ */
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+
+use base::{Color, Layout};
+
+use crate::display::Display;
+
+// Terminal pixels per layout unit. Half blocks make square pixels, and LEDs on a staggered strip
+// are half a unit apart, so two keeps every LED visible.
+const PIXELS_PER_UNIT: f64 = 2.0;
+
+pub struct TerminalDisplay {
+    leds: Vec<Color>,
+    shown: Vec<Color>,
+    // Terminal pixel of each display LED, as (column, row).
+    pixels: Vec<(usize, usize)>,
+    columns: usize,
+    rows: usize,  // In pixels, so twice the terminal lines.
+    cleared: bool,
+}
+
+impl TerminalDisplay {
+    pub fn new(layout: &Layout) -> Self {
+        let positions = layout.display_positions();
+        let min_x = positions.iter().map(|&(x, _)| x).fold(f64::INFINITY, f64::min);
+        let min_y = positions.iter().map(|&(_, y)| y).fold(f64::INFINITY, f64::min);
+        let to_pixel = |value: f64, min: f64| ((value - min) * layout.scale * PIXELS_PER_UNIT).round() as usize;
+        let pixels: Vec<(usize, usize)> = positions.iter()
+            .map(|&(x, y)| (to_pixel(x, min_x), to_pixel(y, min_y)))
+            .collect();
+        let columns = pixels.iter().map(|&(column, _)| column + 1).max().unwrap_or(0);
+        let rows = pixels.iter().map(|&(_, row)| row + 2).max().unwrap_or(0) / 2 * 2;
+        println!("Using a terminal display");
+        TerminalDisplay { leds: vec![Color::black(); positions.len()], shown: Vec::new(),
+                          pixels: pixels, columns: columns, rows: rows, cleared: false }
+    }
+
+    fn draw(&self) -> String {
+        let mut grid: Vec<Option<Color>> = vec![None; self.columns * self.rows];
+        for (&(column, row), &color) in self.pixels.iter().zip(self.leds.iter()) {
+            grid[row * self.columns + column] = Some(color);
+        }
+        // Home the cursor and draw over the last frame.
+        let mut out = String::from("\x1b[H");
+        for line in 0..self.rows / 2 {
+            for column in 0..self.columns {
+                let top = grid[line * 2 * self.columns + column];
+                let bottom = grid[(line * 2 + 1) * self.columns + column];
+                match (top, bottom) {
+                    (None, None) => out.push_str("\x1b[0m "),
+                    (top, bottom) => {
+                        let (top, bottom) = (top.unwrap_or(Color::black()), bottom.unwrap_or(Color::black()));
+                        write!(out, "\x1b[38;2;{};{};{};48;2;{};{};{}m\u{2580}",
+                               top.r, top.g, top.b, bottom.r, bottom.g, bottom.b).unwrap();
+                    }
+                }
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+}
+
+impl Display for TerminalDisplay {
+    fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8) {
+        if let Some(led) = self.leds.get_mut(index) {
+            *led = Color{r: r, g: g, b: b};
+        }
+    }
+    fn show(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.leds == self.shown {
+            return Ok(());
+        }
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        if !self.cleared {
+            // Clear the screen and hide the cursor.
+            out.write_all(b"\x1b[2J\x1b[?25l")?;
+            self.cleared = true;
+        }
+        out.write_all(self.draw().as_bytes())?;
+        out.flush()?;
+        self.shown = self.leds.clone();
+        Ok(())
+    }
+}
+
+impl Drop for TerminalDisplay {
+    fn drop(&mut self) {
+        if self.cleared {
+            print!("\x1b[0m\x1b[?25h");
+        }
+    }
+}
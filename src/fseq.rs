@@ -0,0 +1,238 @@
+/**
+ * Reads xLights .fseq sequence files, versions 1 and 2.
+ *
+ * Both versions start with a fixed little endian header:
+ *   0  "PSEQ" (or "FSEQ" in old files)
+ *   4  u16 offset of the channel data
+ *   6  u8 minor version, u8 major version
+ *   8  u16 header length
+ *   10 u32 channels per frame
+ *   14 u32 frame count
+ *   18 u8 milliseconds per frame
+ * Version 1 frames follow uncompressed. Version 2 adds a compression type, an index of
+ * compressed blocks (each holding a run of frames) and optional sparse channel ranges, which
+ * mean only those channels are stored.
+ */
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_ZSTD: u8 = 1;
+const COMPRESSION_ZLIB: u8 = 2;
+
+// A decoded sequence, with every frame expanded to the full channel range.
+pub struct Sequence {
+    pub channels: usize,
+    pub frames: usize,
+    pub frame_ms: u32,
+    data: Vec<u8>,
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> usize {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]]) as usize
+}
+
+fn u24_at(bytes: &[u8], at: usize) -> usize {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], 0]) as usize
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> usize {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) as usize
+}
+
+impl Sequence {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut file = File::open(path)?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        Sequence::parse(&contents).map_err(|e| format!("{}: {}", path, e).into())
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() < 28 || !(&bytes[0..4] == b"PSEQ" || &bytes[0..4] == b"FSEQ") {
+            return Err("Not an fseq file".into());
+        }
+        let data_offset = u16_at(bytes, 4);
+        let major_version = bytes[7];
+        let stored_channels = u32_at(bytes, 10);
+        let frames = u32_at(bytes, 14);
+        let frame_ms = bytes[18] as u32;
+        if frames == 0 || frame_ms == 0 {
+            return Err("Sequence is empty".into());
+        }
+        if data_offset > bytes.len() {
+            return Err("Channel data is past the end of the file".into());
+        }
+        let (stored, ranges) = match major_version {
+            1 => (bytes[data_offset..].to_vec(), Vec::new()),
+            2 => Sequence::read_v2(bytes, data_offset, stored_channels, frames)?,
+            _ => return Err(format!("Unsupported fseq version {}", major_version).into()),
+        };
+        if stored.len() < frames * stored_channels {
+            return Err(format!("Expected {} frames of {} channels but found {} bytes",
+                               frames, stored_channels, stored.len()).into());
+        }
+        if ranges.len() == 0 {
+            let data = stored[..frames * stored_channels].to_vec();
+            return Ok(Sequence { channels: stored_channels, frames: frames, frame_ms: frame_ms, data: data });
+        }
+
+        // Spread the stored channels back out over their ranges.
+        let channels = ranges.iter().map(|&(start, count)| start + count).max().unwrap();
+        let mut data = vec![0; frames * channels];
+        for frame in 0..frames {
+            let mut from = frame * stored_channels;
+            for &(start, count) in ranges.iter() {
+                let to = frame * channels + start;
+                data[to..to + count].copy_from_slice(&stored[from..from + count]);
+                from += count;
+            }
+        }
+        Ok(Sequence { channels: channels, frames: frames, frame_ms: frame_ms, data: data })
+    }
+
+    // Returns the stored frames, decompressed, and the sparse (start, count) channel ranges.
+    fn read_v2(bytes: &[u8], data_offset: usize, stored_channels: usize, frames: usize)
+               -> Result<(Vec<u8>, Vec<(usize, usize)>), Box<dyn Error>> {
+        let compression = bytes[20] & 0x0F;
+        let block_count = ((bytes[20] as usize & 0xF0) << 4) | bytes[21] as usize;
+        let range_count = bytes[22] as usize;
+        let index_end = 32 + block_count * 8;
+        let ranges_end = index_end + range_count * 6;
+        if ranges_end > data_offset {
+            return Err("Header is longer than the channel data offset".into());
+        }
+
+        let mut ranges = Vec::with_capacity(range_count);
+        for range in 0..range_count {
+            let at = index_end + range * 6;
+            ranges.push((u24_at(bytes, at), u24_at(bytes, at + 3)));
+        }
+        let sparse_channels: usize = ranges.iter().map(|&(_, count)| count).sum();
+        if range_count > 0 && sparse_channels != stored_channels {
+            return Err(format!("Sparse ranges cover {} channels but frames hold {}",
+                               sparse_channels, stored_channels).into());
+        }
+
+        if compression == COMPRESSION_NONE {
+            return Ok((bytes[data_offset..].to_vec(), ranges));
+        }
+        if compression != COMPRESSION_ZSTD {
+            let name = if compression == COMPRESSION_ZLIB {"zlib"} else {"unknown"};
+            return Err(format!("Unsupported {} compression; re-export the sequence with zstd",
+                               name).into());
+        }
+
+        let mut stored = Vec::with_capacity(frames * stored_channels);
+        let mut at = data_offset;
+        for block in 0..block_count {
+            let length = u32_at(bytes, 32 + block * 8 + 4);
+            if length == 0 {
+                continue;  // xLights pads the index with empty blocks.
+            }
+            if at + length > bytes.len() {
+                return Err(format!("Block {} is past the end of the file", block).into());
+            }
+            stored.extend(zstd::stream::decode_all(&bytes[at..at + length])?);
+            at += length;
+        }
+        Ok((stored, ranges))
+    }
+
+    pub fn frame(&self, frame: usize) -> &[u8] {
+        &self.data[frame * self.channels..(frame + 1) * self.channels]
+    }
+
+    pub fn duration(&self) -> f32 { self.frames as f32 * self.frame_seconds() }
+    pub fn frame_seconds(&self) -> f32 { self.frame_ms as f32 / 1000.0 }
+}
+
+// Sequences loaded so far, by path, so every area of a scene plays from the same copy.
+#[derive(Clone, Default)]
+pub struct SequenceCache(Arc<Mutex<HashMap<String, Arc<Sequence>>>>);
+
+impl SequenceCache {
+    pub fn load(&self, path: &str) -> Result<Arc<Sequence>, Box<dyn Error>> {
+        let mut sequences = self.0.lock().unwrap();
+        if let Some(sequence) = sequences.get(path) {
+            return Ok(sequence.clone());
+        }
+        let sequence = Arc::new(Sequence::load(path)?);
+        sequences.insert(path.to_string(), sequence.clone());
+        Ok(sequence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A header for frames of 25ms, with the channel data starting at data_offset.
+    fn header(major_version: u8, data_offset: usize, channels: usize, frames: usize) -> Vec<u8> {
+        let mut bytes = vec![0; data_offset];
+        bytes[0..4].copy_from_slice(b"PSEQ");
+        bytes[4..6].copy_from_slice(&(data_offset as u16).to_le_bytes());
+        bytes[7] = major_version;
+        bytes[8..10].copy_from_slice(&(data_offset as u16).to_le_bytes());
+        bytes[10..14].copy_from_slice(&(channels as u32).to_le_bytes());
+        bytes[14..18].copy_from_slice(&(frames as u32).to_le_bytes());
+        bytes[18] = 25;
+        bytes
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let mut bytes = header(1, 28, 3, 2);
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        let sequence = Sequence::parse(&bytes).unwrap();
+        assert_eq!((sequence.channels, sequence.frames, sequence.frame_ms), (3, 2, 25));
+        assert_eq!(sequence.frame(0), &[1, 2, 3]);
+        assert_eq!(sequence.frame(1), &[4, 5, 6]);
+        assert_eq!(sequence.duration(), 0.05);
+    }
+
+    #[test]
+    fn reads_uncompressed_version_2() {
+        let mut bytes = header(2, 32, 3, 2);
+        bytes[20] = COMPRESSION_NONE;
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        let sequence = Sequence::parse(&bytes).unwrap();
+        assert_eq!(sequence.frame(0), &[1, 2, 3]);
+        assert_eq!(sequence.frame(1), &[4, 5, 6]);
+    }
+
+    #[test]
+    fn reads_zstd_blocks_with_sparse_ranges() {
+        // A block per frame plus an empty one, then channels 2-3 and 6.
+        let blocks = [zstd::stream::encode_all(&[1, 2, 3][..], 0).unwrap(),
+                      zstd::stream::encode_all(&[4, 5, 6][..], 0).unwrap(),
+                      Vec::new()];
+        let ranges = [(2, 2), (6, 1)];
+        let data_offset = 32 + blocks.len() * 8 + ranges.len() * 6;
+        let mut bytes = header(2, data_offset, 3, 2);
+        bytes[20] = COMPRESSION_ZSTD;
+        bytes[21] = blocks.len() as u8;
+        bytes[22] = ranges.len() as u8;
+        for (index, block) in blocks.iter().enumerate() {
+            let at = 32 + index * 8;
+            bytes[at..at + 4].copy_from_slice(&(index as u32).to_le_bytes());
+            bytes[at + 4..at + 8].copy_from_slice(&(block.len() as u32).to_le_bytes());
+        }
+        for (index, &(start, count)) in ranges.iter().enumerate() {
+            let at = 32 + blocks.len() * 8 + index * 6;
+            bytes[at..at + 3].copy_from_slice(&(start as u32).to_le_bytes()[..3]);
+            bytes[at + 3..at + 6].copy_from_slice(&(count as u32).to_le_bytes()[..3]);
+        }
+        for block in blocks.iter() {
+            bytes.extend_from_slice(block);
+        }
+
+        let sequence = Sequence::parse(&bytes).unwrap();
+        assert_eq!(sequence.channels, 7);
+        assert_eq!(sequence.frame(0), &[0, 0, 1, 2, 0, 0, 3]);
+        assert_eq!(sequence.frame(1), &[0, 0, 4, 5, 0, 0, 6]);
+    }
+}
@@ -1,114 +1,211 @@
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use base::Color;
-use base::PainterParams;
+use base::{Color, ColorCorrection, FloatColor};
+use base::{Config, DisplayConfig, DisplayKind, RunnerKind};
+use base::Layout;
+use base::{FrameStatus, PowerStatus};
+use base::{Command, PainterParams, ParamsCheck, Playlist, SequenceParams, TransitionKind, TransitionParams};
 use base::rocket_server;
 
+mod audio;
+mod clock;
+mod compositor;
 mod display;
+mod fseq;
+mod output;
 mod painter;
-use painter::{Bounds,Painter};
-
-#[cfg_attr(feature = "emulator", path = "runner/emulator.rs")]
-#[cfg_attr(not(feature = "emulator"), path = "runner/default_runner.rs")]
-pub mod runner;
+mod player;
+#[cfg(feature = "render")]
+mod render;
+mod runner;
+mod scene;
+mod transition;
+use scene::{Garment, Scene};
+use transition::Transition;
 
+// Used when there are no saved params.
+fn default_params() -> PainterParams {
+    PainterParams {
+        painter: String::from("hex"),
+        global_brightness: 0.1,
+        speed: 0.8,
+        color: Color::new(0xFFFFFF),
+        secondary_colors: vec![
+            Color::new(0x4267B2),  // FB blue.
+            Color::new(0x898F9C),  // FB grey.
+            Color::new(0xAC0000),
+            Color::new(0x8A8A00),
+            Color::new(0x8A008A),
+        ],
+        fade: 0.9,
+        bidirectional: true,
+        fade_after: true,
+        color_index: 0,
+        belt_only: false,
+        layers: Vec::new(),
+        zones: BTreeMap::new(),
+        transition: TransitionParams::default(),
+        sequence: SequenceParams::default(),
+    }
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
 
-    let mut params = match PainterParams::load() {
+    let mut base_params = match PainterParams::load() {
         Ok(loaded_params) => loaded_params,
         Err(e) => {
             println!("Unable to load from file: {}", e);
-            PainterParams {
-                painter: String::from("hex"),
-                global_brightness: 0.1,
-                speed: 0.8,
-                color: Color::new(0xFFFFFF),
-                secondary_colors: vec![
-                    Color::new(0x4267B2),  // FB blue.
-                    Color::new(0x898F9C),  // FB grey.
-                    Color::new(0xAC0000),
-                    Color::new(0x8A8A00),
-                    Color::new(0x8A008A),
-                ],
-                fade: 0.9,
-                bidirectional: true,
-                fade_after: true,
-                color_index: 0,
-                belt_only: false,
-            }
+            default_params()
         }
     };
 
-    let webserver = rocket_server(params.clone())?;
+    let layout = Layout::load("layout.json")?;
 
-    params.apply_dimming();  // Apply dimming after caching the web version.
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 && args[1] == "render" {
+        #[cfg(feature = "render")]
+        return render::run(&args[2..], &layout, base_params);
+        #[cfg(not(feature = "render"))]
+        return Err("Rendering needs the render feature".into());
+    }
+    let config = Config::from_args(&args[1..])?;
+    let zones = layout.segments.iter().map(|segment| segment.name.clone()).collect();
 
-    let all_areas = vec![
-        Bounds{height: 30, width: 16},
-        Bounds{height: 30, width: 4},
-    ];
+    let playlist = match Playlist::load() {
+        Ok(loaded_playlist) => loaded_playlist,
+        Err(_) => Playlist::new(),
+    };
 
-    let belt = vec![
-        Bounds{height: 22, width: 4},
-    ];
+    let power = Arc::new(Mutex::new(PowerStatus::default()));
+    let frames = Arc::new(Mutex::new(FrameStatus::default()));
 
-    let all_areas_full_size: usize = all_areas.iter().map(|&x: &Bounds| x.size()).sum();
-    let all_areas_size: usize = all_areas_full_size - 1;
-    let belt_size: usize = belt.iter().map(|&x: &Bounds| x.size()).sum();
+    let all_areas = Garment::new(&layout, false);
+    let belt = Garment::new(&layout, true);
+    let all_areas_size: usize = all_areas.size();
 
-    // Remember to enable spi via raspi-config!
-    let mut display = runner::get_display(all_areas_size)?;
+    // Saved params can name a painter this build doesn't have, or a sequence that has gone
+    // missing. Start on the defaults rather than not at all, since the suit has no other way to
+    // recover.
+    let mut scene = match Scene::new(if base_params.belt_only {belt.clone()} else {all_areas.clone()},
+                                     base_params.clone()) {
+        Ok(scene) => scene,
+        Err(e) => {
+            println!("Unable to paint the saved params, so using the defaults: {}", e);
+            base_params = default_params();
+            Scene::new(all_areas.clone(), base_params.clone())?
+        },
+    };
+    let params = base_params.clone();
 
-    let areas = if params.belt_only {&belt} else {&all_areas};
-    if params.belt_only {
-        display.set_offset(all_areas_size);
-    } else {
-        display.set_offset(0);
+    let (check_suit, check_belt) = (all_areas.clone(), belt.clone());
+    let check: ParamsCheck = Box::new(move |params| {
+        Scene::new(if params.belt_only {check_belt.clone()} else {check_suit.clone()}, params.clone())?;
+        Ok(())
+    });
+    let webserver = rocket_server(base_params.clone(), playlist.clone(), painter::painter_infos(), check, zones,
+                                  power.clone(), frames.clone())?;
+    let mut player = player::Player::new(playlist);
+
+    let mut displays = if config.displays.len() > 0 {config.displays.clone()} else {vec![display::default_config()]};
+    if let Some(path) = config.record.as_ref() {
+        displays.push(DisplayConfig::new(DisplayKind::Record { path: path.clone() }));
     }
-    let mut painters: Vec<Box<dyn Painter>> = areas.iter().map(|&x: &Bounds| {
-        painter::make_painter(x, params.clone())
-    }).collect();
-
-    runner::run(move || {
-        let mut led: usize = 0;
-        let mut idx: usize = 0;
-        for painter in painters.iter_mut() {
-            painter.paint();
-            for pix in 0..painter.length() {
-                // I derped and borked the first LED on the sleeve x.x
-                if idx == 1 && pix == 0 {
-                    continue;
-                }
-                let pixel = painter.get(pix);
-                display.set_pixel(led, pixel.r, pixel.g, pixel.b);
-                led += 1;
+    let runner_kind = config.runner.unwrap_or(
+        if displays.iter().any(|x| x.kind == DisplayKind::Emulator) {RunnerKind::Emulator} else {RunnerKind::Timer});
+    // Remember to enable spi via raspi-config!
+    let output_layout = layout.clone();
+    // Recordings hold the colors as they were sent out, after the brightness and any correction,
+    // so a replay gets neither again.
+    let color = if config.replay.is_some() {ColorCorrection::identity()} else {config.color.clone()};
+    let power_config = config.power;
+    let mut output = output::Output::start(move || {
+        display::from_configs(&displays, &output_layout, &color, power_config, power)
+    }, frames)?;
+
+    // Play back a recording instead of running the painters.
+    if let Some(path) = config.replay.as_ref() {
+        let mut replay = display::recorder::Replay::open(path)?;
+        let mut pixels = Vec::new();
+        return runner::run(runner_kind, move || {
+            let started = Instant::now();
+            match replay.frame() {
+                Ok(frame) => {
+                    pixels.clear();
+                    pixels.extend(frame.iter().map(|&pixel| FloatColor::from(pixel)));
+                    output.submit(started, &pixels, 0, 1.0);
+                },
+                Err(e) => println!("Unable to read recording: {}", e),
             }
-            idx += 1;
+        });
+    }
+
+    let mut brightness = params.global_brightness;
+
+    let mut transition: Option<Transition> = None;
+    // e.g. --audio alsa:default or --audio wav:test.wav
+    let audio = match config.audio.as_ref() {
+        Some(spec) => Some(audio::start(spec)?),
+        None => None,
+    };
+    let mut clock = clock::Clock::new(audio);
+
+    runner::run(runner_kind, move || {
+        let started = Instant::now();
+        let time = clock.tick();
+        scene.paint(&time);
+        let (frame, belt_only) = match transition.as_mut() {
+            Some(transition) => transition.render(&scene, &time),
+            None => (scene.frame(), scene.garment.belt_only),
+        };
+        // Frames sent to the display have the belt after the suit.
+        let first_led = if belt_only {all_areas_size} else {0};
+        output.submit(started, frame, first_led, brightness);
+        if transition.as_ref().map_or(false, |transition| transition.finished()) {
+            transition = None;
         }
-        display.show().unwrap();
+
+        let mut changed = player.tick();
         match webserver.try_recv() {
-            Ok(new_params) => {
-                if new_params.belt_only != params.belt_only || new_params.painter != params.painter {
-                    let areas = if new_params.belt_only {&belt} else {&all_areas};
-                    painters = areas.iter().map(|&x: &Bounds| {
-                        painter::make_painter(x, new_params.clone())
-                    }).collect();
-
-                    if new_params.belt_only != params.belt_only {
-                        /*
-                        let dots: usize = areas.iter().map(|&x: &Bounds| x.size()).sum();
-                        display.set_count(dots);
-                         */
-                    }
-                } else {
-                    for painter in painters.iter_mut() {
-                        painter.set_params(new_params.clone());
-                    }
-                }
-                params = new_params;
+            Ok(Command::Params(new_params)) => {
+                base_params = new_params;
+                changed = true;
+            },
+            Ok(Command::SetPlaylist(new_playlist)) => {
+                player.set_playlist(new_playlist);
+                changed = true;
             },
+            Ok(Command::Playlist(command)) => {
+                player.command(command);
+                changed = true;
+            },
+            Ok(Command::Sequence(command)) => scene.control(command),
             Err(_) => {}
         }
+        if !changed {
+            return;
+        }
+
+        let new_params = player.params(&base_params);
+        brightness = new_params.global_brightness;
+        let garment_changed = new_params.belt_only != scene.garment.belt_only;
+        let cut = new_params.transition.kind == TransitionKind::Cut;
+        if !garment_changed && (cut || !scene.needs_rebuild(&new_params)) {
+            scene.set_params(new_params);
+            return;
+        }
+        let garment = if new_params.belt_only {belt.clone()} else {all_areas.clone()};
+        let settings = new_params.transition;
+        match Scene::new(garment, new_params) {
+            Ok(new_scene) => {
+                let old_scene = std::mem::replace(&mut scene, new_scene);
+                if !cut {
+                    transition = Some(Transition::new(old_scene, &scene, settings));
+                }
+            },
+            Err(e) => println!("Unable to switch painters: {}", e),
+        }
     })
 }
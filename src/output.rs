@@ -0,0 +1,137 @@
+/**
+ * Shows frames from a thread of its own, so painting isn't held up by the displays. The render
+ * side hands over each frame as it is painted, and the output thread always shows the latest
+ * one: a frame it hasn't got to by the time the next is ready is dropped rather than queued, so
+ * a slow display costs frames instead of adding latency.
+ *
+ * Frames go back to the render side once they are shown, to paint the next ones into.
+ *
+ * Errors from the displays are counted rather than printed for every frame: a new error is
+ * printed once, and then again when frames are shown without one.
+ */
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use base::{FloatColor, FrameStatus};
+use crossbeam_channel::{bounded, Receiver, Sender};
+
+use crate::clock::TICK_SECONDS;
+use crate::display::Display;
+
+#[derive(Default)]
+struct Frame {
+    pixels: Vec<FloatColor>,
+    first_led: usize,
+    brightness: f32,
+}
+
+pub struct Output {
+    frames: Option<Sender<Frame>>,  // Only taken to stop the output thread.
+    // The same channel, to take back a frame the output thread hasn't got to yet.
+    unshown: Receiver<Frame>,
+    spares: Receiver<Frame>,  // Frames already shown.
+    status: Arc<Mutex<FrameStatus>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Output {
+    // Makes the display on the output thread, so it never has to be sent between threads.
+    pub fn start<F>(make_display: F, status: Arc<Mutex<FrameStatus>>) -> Result<Self, Box<dyn Error>>
+    where F: FnOnce() -> Result<Box<dyn Display>, Box<dyn Error>> + Send + 'static {
+        let (frames, frame_receiver) = bounded(1);
+        let (spare_sender, spares) = bounded(2);
+        let (started_sender, started) = bounded(1);
+        let output_status = status.clone();
+        let unshown = frame_receiver.clone();
+        let thread = thread::spawn(move || {
+            let display = match make_display() {
+                Ok(display) => display,
+                Err(e) => {
+                    let _ = started_sender.send(Err(e.to_string()));
+                    return;
+                },
+            };
+            let _ = started_sender.send(Ok(()));
+            show_frames(display, frame_receiver, spare_sender, output_status);
+        });
+        started.recv()??;
+        Ok(Output { frames: Some(frames), unshown: unshown, spares: spares, status: status,
+                    thread: Some(thread) })
+    }
+
+    // Copies a frame out to be shown. started is when the frame's tick began, to count it missed
+    // if painting it took longer than a tick.
+    pub fn submit(&mut self, started: Instant, pixels: &[FloatColor], first_led: usize, brightness: f32) {
+        let stale = self.unshown.try_recv().ok();
+        let dropped = stale.is_some();
+        let mut frame = stale.or_else(|| self.spares.try_recv().ok()).unwrap_or_default();
+        frame.pixels.clear();
+        frame.pixels.extend_from_slice(pixels);
+        frame.first_led = first_led;
+        frame.brightness = brightness;
+        if let Some(frames) = self.frames.as_ref() {
+            // Never full, since only this thread sends and it just emptied the channel.
+            let _ = frames.try_send(frame);
+        }
+
+        let mut status = self.status.lock().unwrap();
+        status.rendered += 1;
+        if dropped {
+            status.dropped += 1;
+        }
+        if started.elapsed().as_secs_f32() > TICK_SECONDS {
+            status.missed += 1;
+        }
+    }
+}
+
+impl Drop for Output {
+    // Waits for the output thread to finish, so the displays are closed properly.
+    fn drop(&mut self) {
+        self.frames.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Runs on the output thread until the Output is dropped.
+fn show_frames(mut display: Box<dyn Display>, frames: Receiver<Frame>, spares: Sender<Frame>,
+               status: Arc<Mutex<FrameStatus>>) {
+    let mut last_error: Option<String> = None;
+    let mut failed_frames = 0;
+    for frame in frames.iter() {
+        let started = Instant::now();
+        display.set_brightness(frame.brightness);
+        for (led, &pixel) in frame.pixels.iter().enumerate() {
+            display.set_color(frame.first_led + led, pixel);
+        }
+        let result = display.show();
+        let mut status = status.lock().unwrap();
+        status.shown += 1;
+        if started.elapsed().as_secs_f32() > TICK_SECONDS {
+            status.missed += 1;
+        }
+        match result {
+            Err(e) => {
+                let error = e.to_string();
+                if last_error.as_ref() != Some(&error) {
+                    println!("Unable to show frame: {}", error);
+                    last_error = Some(error);
+                }
+                failed_frames += 1;
+                status.errors += 1;
+            },
+            Ok(()) if failed_frames > 0 => {
+                println!("Showing frames again after {} failed", failed_frames);
+                last_error = None;
+                failed_frames = 0;
+            },
+            Ok(()) => {},
+        }
+        drop(status);
+        let _ = spares.try_send(frame);
+    }
+}
@@ -1,15 +1,27 @@
 extern crate rand;
 use rand::prelude::*;
+use std::error::Error;
+use std::sync::Arc;
 
-use base::Color;
+use base::{Color, FloatColor};
+use base::{display_map, Layout, Segment};
+use base::PainterInfo;
 use base::PainterParams;
+use base::SequenceCommand;
+
+use crate::audio::AudioFeatures;
+use crate::clock::FrameTime;
+use crate::compositor::LayeredPainter;
+use crate::fseq::{Sequence, SequenceCache};
 
 
 pub trait Painter {
-    fn paint(&mut self);
-    fn length(&self) -> usize;
-    fn get(&self, index:usize) -> Color;
+    fn paint(&mut self, time: &FrameTime);
+    // The painted colors, in painter index order.
+    fn leds(&self) -> &[FloatColor];
     fn set_params(&mut self, params: PainterParams);
+    // Transport controls, for painters that play something back.
+    fn control(&mut self, _command: SequenceCommand) {}
 }
 
 #[derive(Copy, Clone)]
@@ -42,11 +54,41 @@ impl Bounds {
     }
 }
 
+// Everything a painter knows about the segment it is painting.
+#[derive(Clone)]
+pub struct Area {
+    pub name: String,  // The segment name, which is also its zone in PainterParams.
+    pub bounds: Bounds,
+    pub region: String,  // From the segment, e.g. "back" or "arm".
+    // Position of each LED across the whole garment, by painter index. See
+    // Layout::normalized_position.
+    pub coords: Vec<(f32, f32)>,
+    // Place of each LED along the chain through every segment in the layout, by painter index,
+    // or None if it is dead or spliced out. Sequences address LEDs in this order.
+    pub chain: Vec<Option<usize>>,
+    // Shared by the areas of a scene. See Scene::new.
+    pub sequences: SequenceCache,
+}
+
+impl Area {
+    pub fn new(layout: &Layout, segment: &Segment) -> Self {
+        let coords = (0..segment.size()).map(|index| {
+            let (x, y) = layout.normalized_position(segment, index);
+            (x as f32, y as f32)
+        }).collect();
+        let position = layout.segments.iter().position(|x| x.name == segment.name).unwrap();
+        let chain = display_map(&layout.segments).swap_remove(position);
+        Area { name: segment.name.clone(), bounds: Bounds{height: segment.height, width: segment.width},
+               region: segment.region.clone(), coords: coords, chain: chain,
+               sequences: SequenceCache::default() }
+    }
+}
+
 struct SweepPainter {
     height: usize,
     width: usize,
     leds: LedString,
-    tick: usize,
+    center: f32,
     params: PainterParams,
     flip: bool,
     bounds: Bounds,
@@ -55,16 +97,15 @@ struct SweepPainter {
 impl SweepPainter {
     fn new(width: usize, height: usize, params: PainterParams) -> Self {
         return SweepPainter { height: height, width: width, params: params,
-                              leds: new_led_string(width * height), tick: 0,
+                              leds: new_led_string(width * height), center: 0.0,
                               flip: false, bounds: Bounds{height, width}};
     }
 }
 
 impl Painter for SweepPainter {
-    fn paint(&mut self) {
-        let speed = self.params.speed;
+    fn paint(&mut self, time: &FrameTime) {
         let growth = 2.0;
-        let center: f32 = self.tick as f32 * speed;
+        let center: f32 = self.center;
         for y in 0..self.height {
             let mut val: f32;
             let y_float = y as f32;
@@ -78,19 +119,18 @@ impl Painter for SweepPainter {
             }
             for x in 0..self.width {
                 let index = get_index(self.height, x, if self.flip {self.bounds.flip_u(y)} else {y});
-                self.leds[index] =  self.params.color * val;
+                self.leds[index] = FloatColor::from(self.params.color) * val;
             }
         }
-        self.tick += 1;
+        self.center += self.params.speed * time.ticks();
         if center > self.height as f32 * growth {
-            self.tick = 0;
+            self.center = 0.0;
             if self.params.bidirectional {
                 self.flip = !self.flip;
             }
         }
     }
-    fn length(&self) -> usize { self.leds.len() }
-    fn get(&self, index: usize) -> Color { self.leds[index] }
+    fn leds(&self) -> &[FloatColor] { &self.leds }
     fn set_params(&mut self, params: PainterParams) { self.params = params; }
 }
 
@@ -104,7 +144,7 @@ fn get_index(height:usize, x:usize, y:usize) -> usize {
 }
 
 // A line of LED's. This may be the entire strand, or a slice of them.
-type LedString = Vec<Color>;
+type LedString = Vec<FloatColor>;
 
 fn fade_all(leds: &mut LedString, fade_multiplier: f32) {
     for i in 0..leds.len() {
@@ -115,18 +155,31 @@ fn fade_all(leds: &mut LedString, fade_multiplier: f32) {
 fn fill_every_other(parity: usize, color: Color, leds: &mut LedString) {
     for i in 0..leds.len() {
         if i % 2 == parity {
-            leds[i] = color;
+            leds[i] = color.into();
         }
     }
 }
 
-fn should_advance(tick: f32, speed: f32) -> bool {
-    return (tick + speed).floor() != tick.floor();
+// Moves tick on by distance and returns how many whole steps it passed. The fraction carries over
+// to the next frame, so painters that move in steps keep their speed at any frame rate.
+fn take_steps(tick: &mut f32, distance: f32) -> usize {
+    *tick += distance;
+    let steps = tick.floor();
+    *tick -= steps;
+    steps.max(0.0) as usize
+}
+
+// Beats heard since the last call. Beats from before the first call don't count, so a painter
+// doesn't start on a beat that happened before it was made.
+fn new_beats(heard_beats: &mut Option<u64>, audio: &AudioFeatures) -> u64 {
+    let beats = heard_beats.map_or(0, |heard| audio.beats.saturating_sub(heard));
+    *heard_beats = Some(audio.beats);
+    beats
 }
 
 fn new_led_string (size: usize) -> LedString {
     let mut led_string = Vec::with_capacity(size);
-    led_string.resize(size, Color::new(0x000000));
+    led_string.resize(size, FloatColor::black());
     return led_string
 }
 
@@ -144,18 +197,21 @@ pub struct HexPainter {
     tick: f32,
     hexes: Vec<Hex>,
     rng: ThreadRng,
-    hold_frames: i32,
-    fade_frames: i32,
+    hold_ticks: f32,
+    fade_ticks: f32,
+    heard_beats: Option<u64>,
 }
 
 impl HexPainter {
     fn new(bounds: Bounds, params: PainterParams) -> Self {
         return HexPainter { bounds: bounds, params: params,
                             leds: new_led_string(bounds.width * bounds.height), tick: 0.0,
-                            hexes: Vec::new(), rng: rand::thread_rng(), hold_frames: 0, fade_frames: 0};
+                            hexes: Vec::new(), rng: rand::thread_rng(), hold_ticks: 0.0, fade_ticks: 0.0,
+                            heard_beats: None};
     }
     // Paint a hexagonal region around [x, y]
     fn paint_hex(&mut self, x: usize, y: f32, color: Color) {
+        let color = FloatColor::from(color);
         assert!(x >= 1);
         assert!(x < self.bounds.width - 1);
         assert!(y >= 1.0);
@@ -169,33 +225,10 @@ impl HexPainter {
         self.leds[self.bounds.get_offset_index(x + 1, y - 0.5)] = color;
         self.leds[self.bounds.get_offset_index(x + 1, y + 0.5)] = color;
     }
-}
-
-impl Painter for HexPainter {
-    fn paint(&mut self) {
-        let advance: bool = should_advance(self.tick, self.params.speed / 2.0);
-        self.tick += self.params.speed / 2.0;
+    // Paints the hexes, and if advance, moves on to the ring around them. Returns false once there
+    // is no room left to grow.
+    fn grow(&mut self, advance: bool) -> bool {
         let mut new_hexes: Vec<Hex> = Vec::new();
-        if !self.params.fade_after {
-            fade_all(&mut self.leds, self.params.fade);
-        }
-        if self.hold_frames > 0 {
-            self.hold_frames -= 1;
-            if self.hold_frames == 0 {
-                self.fade_frames = 60;
-            }
-            return;
-        }
-        if self.fade_frames > 0 {
-            self.fade_frames -= 1;
-            fade_all(&mut self.leds, self.params.fade);
-            return;
-        }
-        if self.hexes.len() == 0 {
-            self.hexes.push(Hex{x: self.bounds.width as i32 - 2,
-                                y: 1.0,
-                                color: self.params.next_color()});
-        }
         for i in 0..self.hexes.len() {
             let x = self.hexes[i].x;
             let y = self.hexes[i].y;
@@ -238,11 +271,11 @@ impl Painter for HexPainter {
             self.hexes = Vec::new();
             if new_hexes.len() == 0 {
                 if self.params.fade_after {
-                    self.hold_frames = 40;
+                    self.hold_ticks = 40.0;
                 } else {
-                    self.fade_frames = 40;
+                    self.fade_ticks = 40.0;
                 }
-                return;
+                return false;
             }
             let mut pick: Vec<f32> = Vec::new();
             pick.resize_with(new_hexes.len(), || { self.rng.gen() });
@@ -253,10 +286,46 @@ impl Painter for HexPainter {
                 }
             }
         }
+        true
     }
+}
 
-    fn length(&self) -> usize { self.leds.len() }
-    fn get(&self, index: usize) -> Color { self.leds[index] }
+impl Painter for HexPainter {
+    fn paint(&mut self, time: &FrameTime) {
+        let mut steps = take_steps(&mut self.tick, self.params.speed / 2.0 * time.ticks());
+        let beats = new_beats(&mut self.heard_beats, &time.audio);
+        if time.audio.playing() {
+            // Grow on the beat instead.
+            steps = beats as usize;
+        }
+        if !self.params.fade_after {
+            fade_all(&mut self.leds, time.fade(self.params.fade));
+        }
+        if self.hold_ticks > 0.0 {
+            self.hold_ticks -= time.ticks();
+            if self.hold_ticks <= 0.0 {
+                self.fade_ticks = 60.0;
+            }
+            return;
+        }
+        if self.fade_ticks > 0.0 {
+            self.fade_ticks -= time.ticks();
+            fade_all(&mut self.leds, time.fade(self.params.fade));
+            return;
+        }
+        if self.hexes.len() == 0 {
+            self.hexes.push(Hex{x: self.bounds.width as i32 - 2,
+                                y: 1.0,
+                                color: self.params.next_color()});
+        }
+        // Paint every ring grown through, until the hexes run out of room.
+        for step in 0..steps.max(1) {
+            if !self.grow(step < steps) {
+                break;
+            }
+        }
+    }
+    fn leds(&self) -> &[FloatColor] { &self.leds }
     fn set_params(&mut self, params: PainterParams) { self.params = params; }
 }
 
@@ -274,7 +343,7 @@ impl FadePainter {
     }
 }
 impl Painter for FadePainter {
-    fn paint(&mut self) {
+    fn paint(&mut self, time: &FrameTime) {
         let length: f32 = 0.7;
         for y in 0..self.bounds.height {
             for x in 0..self.bounds.width {
@@ -282,16 +351,15 @@ impl Painter for FadePainter {
                 let index: f32 = ((self.tick - offset_y) / (self.bounds.height as f32) / length) %
                     (self.params.secondary_colors.len() as f32);
                 let color: Color = self.params.secondary_colors[index as usize];
-                self.leds[self.bounds.get_offset_index(x, offset_y)] = color;
+                self.leds[self.bounds.get_offset_index(x, offset_y)] = color.into();
             }
         }
-        self.tick += self.params.speed;
+        self.tick += self.params.speed * time.ticks();
         if self.tick >= (self.bounds.height * self.params.secondary_colors.len()) as f32 * length * 2.0 {
             self.tick -= (self.bounds.height * self.params.secondary_colors.len()) as f32 * length;
         }
     }
-    fn length(&self) -> usize { self.leds.len() }
-    fn get(&self, index: usize) -> Color { self.leds[index] }
+    fn leds(&self) -> &[FloatColor] { &self.leds }
     fn set_params(&mut self, params: PainterParams) { self.params = params; }
 }
 
@@ -322,22 +390,14 @@ impl LinePainter {
         line.trails.push(Trail {head_x: width as i32 - 1, head_y: 0.5, x_dir: 0, y_dir: 1.0, y_diag_start: 20.});
         return line;
     }
-}
 
-impl Painter for LinePainter {
-    fn paint(&mut self) {
-        // Advance on integers.
-        self.tick += self.params.speed;
-        let advance: bool = self.tick >= 1.0;  // Hex's are about 2 leds large
-        if advance {
-            self.tick = 0.0;
-        }
-        fade_all(&mut self.leds, self.params.fade);
+    // Paints the heads of the trails, then moves them on a LED if advance.
+    fn step(&mut self, advance: bool) {
         let mut reset = false;
         for mut trail in self.trails.iter_mut() {
             if self.bounds.in_(trail.head_x, trail.head_y) {
                 self.leds[self.bounds.get_offset_index(trail.head_x as usize, trail.head_y)] =
-                    self.params.secondary_colors[self.color_index % self.params.secondary_colors.len()];
+                    self.params.secondary_colors[self.color_index % self.params.secondary_colors.len()].into();
             }
             if advance {
                 if trail.head_y.floor() == trail.y_diag_start.floor() && trail.x_dir == 0 {
@@ -371,10 +431,20 @@ impl Painter for LinePainter {
                 }
             }
         }
+    }
+}
 
+impl Painter for LinePainter {
+    fn paint(&mut self, time: &FrameTime) {
+        // Advance on integers.
+        let steps = take_steps(&mut self.tick, self.params.speed * time.ticks());
+        fade_all(&mut self.leds, time.fade(self.params.fade));
+        // Paint every LED passed, so fast trails don't leave gaps.
+        for step in 0..steps.max(1) {
+            self.step(step < steps);
+        }
     }
-    fn length(&self) -> usize { self.leds.len() }
-    fn get(&self, index: usize) -> Color { self.leds[index] }
+    fn leds(&self) -> &[FloatColor] { &self.leds }
     fn set_params(&mut self, params: PainterParams) { self.params = params; }
 }
 
@@ -398,25 +468,12 @@ impl Raindrops {
                                    leds: new_led_string(bounds.width * bounds.height), tick: 0.0,
                                    trails: trails, rng: rng};
     }
-}
 
-impl Painter for Raindrops {
-    fn length(&self) -> usize { self.leds.len() }
-    fn get(&self, index: usize) -> Color { self.leds[index] }
-    fn set_params(&mut self, params: PainterParams) { self.params = params; }
-    fn paint(&mut self) {
-        // Advance on integers.
-        self.tick += self.params.speed;
-        let advance: bool = self.tick >= 1.0;
-        if advance {
-            self.tick = 0.0;
-        }
-        for idx in 0..self.leds.len() {
-            self.leds[idx] *= self.params.fade;
-        }
+    // Paints the drops, then moves them on a LED if advance.
+    fn step(&mut self, advance: bool) {
         for mut trail in self.trails.iter_mut() {
             if self.bounds.in_y(trail.head_y) {
-                self.leds[get_index(self.bounds.height, trail.head_x as usize, trail.head_y as usize)] = self.params.color;
+                self.leds[get_index(self.bounds.height, trail.head_x as usize, trail.head_y as usize)] = self.params.color.into();
             }
             if advance {
                 trail.head_y += trail.y_dir;
@@ -435,11 +492,30 @@ impl Painter for Raindrops {
     }
 }
 
+impl Painter for Raindrops {
+    fn leds(&self) -> &[FloatColor] { &self.leds }
+    fn set_params(&mut self, params: PainterParams) { self.params = params; }
+    fn paint(&mut self, time: &FrameTime) {
+        let mut speed = self.params.speed;
+        if time.audio.active {
+            // Pour with the bass.
+            speed *= 0.25 + 1.5 * (time.audio.bands[0] + time.audio.bands[1]) / 2.0;
+        }
+        // Advance on integers.
+        let steps = take_steps(&mut self.tick, speed * time.ticks());
+        fade_all(&mut self.leds, time.fade(self.params.fade));
+        // Paint every LED passed, so fast drops don't leave gaps.
+        for step in 0..steps.max(1) {
+            self.step(step < steps);
+        }
+    }
+}
+
 struct Disco {
     params: PainterParams,
     leds: LedString,
-    tick: f64,
-    last_beat: f64,
+    beat: f64,
+    heard_beats: Option<u64>,
 }
 
 impl Disco {
@@ -450,48 +526,248 @@ impl Disco {
         if params.secondary_colors.len() < 2 {
             params.secondary_colors.push(Color::new(0x0000FF));
         }
-        Disco {params: params, leds: new_led_string(bounds.size()), tick: 0.0,
-               last_beat: 0.0}
+        Disco {params: params, leds: new_led_string(bounds.size()), beat: 0.0, heard_beats: None}
     }
 }
 
 impl Painter for Disco {
-    fn length(&self) -> usize { self.leds.len() }
-    fn get(&self, index: usize) -> Color { self.leds[index] }
+    fn leds(&self) -> &[FloatColor] { &self.leds }
     fn set_params(&mut self, params: PainterParams) { self.params = params; }
 
-    fn paint(&mut self) {
+    fn paint(&mut self, time: &FrameTime) {
         let bpm: f64 = (self.params.speed * 130.0).into();  // normalize to 130 bpm
         let bps: f64 = bpm / 60.0;
-        let second: f64 = self.tick * 0.03;
-        let beat: f64 = bps * second;
-        fade_all(&mut self.leds, self.params.fade);
-        if self.last_beat.floor() != beat.floor() {
+        // From the shared clock rather than counted per painter, so every segment strobes together.
+        let mut beat: f64 = bps * time.seconds;
+        let beats = new_beats(&mut self.heard_beats, &time.audio);
+        if time.audio.playing() {
+            // Strobe on the music's beats instead.
+            beat = self.beat.floor() + if beats > 0 {1.0} else {0.0};
+        }
+        fade_all(&mut self.leds, time.fade(self.params.fade));
+        if self.beat.floor() != beat.floor() {
             fill_every_other((beat.floor() as usize) % 2,
                            self.params.next_color(),
                            &mut self.leds);
         }
 
-        self.last_beat = beat;
-        self.tick += 1.0;
+        self.beat = beat;
+    }
+}
+
+// A pattern defined everywhere on the garment rather than per segment. Painters built from the
+// same field at the same time stay in step, so the pattern flows from one segment to the next.
+// region is the part of the body the LED is on, for fields that follow its shape.
+trait Field {
+    fn sample(&self, x: f32, y: f32, region: &str, time: f32, params: &PainterParams) -> FloatColor;
+}
+
+// Blends between the secondary colors, treating them as a repeating gradient.
+fn gradient(colors: &[Color], position: f32) -> FloatColor {
+    if colors.is_empty() {
+        return FloatColor::black();
+    }
+    let position = position.rem_euclid(colors.len() as f32);
+    // rem_euclid can round up to exactly len for tiny negative positions.
+    let index = position.floor() as usize % colors.len();
+    let blend = position - position.floor();
+    FloatColor::from(colors[index]) * (1.0 - blend) + FloatColor::from(colors[(index + 1) % colors.len()]) * blend
+}
+
+// Horizontal bands of the secondary colors travelling down the body.
+struct Wave;
+
+impl Field for Wave {
+    fn sample(&self, x: f32, y: f32, region: &str, time: f32, params: &PainterParams) -> FloatColor {
+        let band_height: f32 = 0.25;
+        // The belt wraps around the waist, so the bands go around it instead of down.
+        let position = if region == "waist" {x} else {y};
+        gradient(&params.secondary_colors, (position - time) / band_height)
+    }
+}
+
+// Rings of the secondary colors spreading out from the middle of the back.
+struct Ripple;
+
+impl Field for Ripple {
+    fn sample(&self, x: f32, y: f32, _region: &str, time: f32, params: &PainterParams) -> FloatColor {
+        let ring_width: f32 = 0.15;
+        let distance = ((x - 0.5).powi(2) + (y - 0.4).powi(2)).sqrt();
+        gradient(&params.secondary_colors, (distance - time) / ring_width)
+    }
+}
+
+struct FieldPainter<F: Field> {
+    field: F,
+    region: String,
+    coords: Vec<(f32, f32)>,
+    params: PainterParams,
+    leds: LedString,
+    time: f32,
+}
+
+impl<F: Field> FieldPainter<F> {
+    fn new(field: F, area: &Area, params: PainterParams) -> Self {
+        FieldPainter { field: field, region: area.region.clone(), coords: area.coords.clone(),
+                       params: params, leds: new_led_string(area.coords.len()), time: 0.0 }
+    }
+}
+
+impl<F: Field> Painter for FieldPainter<F> {
+    fn paint(&mut self, time: &FrameTime) {
+        for (index, &(x, y)) in self.coords.iter().enumerate() {
+            self.leds[index] = self.field.sample(x, y, &self.region, self.time, &self.params);
+        }
+        // Roughly one LED per tick at full speed.
+        self.time += self.params.speed / 46.0 * time.ticks();
+    }
+    fn leds(&self) -> &[FloatColor] { &self.leds }
+    fn set_params(&mut self, params: PainterParams) { self.params = params; }
+}
+
+// Plays back an xLights sequence, taking each LED's color from its three channels.
+struct FseqPainter {
+    chain: Vec<Option<usize>>,
+    params: PainterParams,
+    sequences: SequenceCache,
+    sequence: Arc<Sequence>,
+    position: f32,  // Seconds from the start of the sequence.
+    playing: bool,
+    leds: LedString,
+}
+
+fn load_sequence(sequences: &SequenceCache, params: &PainterParams) -> Result<Arc<Sequence>, Box<dyn Error>> {
+    if params.sequence.file.is_empty() {
+        return Err("No sequence file set".into());
     }
+    sequences.load(&params.sequence.file)
 }
 
-pub fn make_painter(bounds: Bounds, params: PainterParams) -> Box<dyn Painter> {
-    if params.painter == "hex" {
-        return Box::new(HexPainter::new(bounds, params));
+impl FseqPainter {
+    fn new(area: &Area, params: PainterParams) -> Result<Self, Box<dyn Error>> {
+        let sequence = load_sequence(&area.sequences, &params)?;
+        Ok(FseqPainter { chain: area.chain.clone(), params: params, sequences: area.sequences.clone(),
+                         sequence: sequence, position: 0.0, playing: true,
+                         leds: new_led_string(area.chain.len()) })
     }
-    if params.painter == "line" {
-        return Box::new(LinePainter::new(bounds.width, bounds.height, params));
+}
+
+impl Painter for FseqPainter {
+    fn paint(&mut self, time: &FrameTime) {
+        let frame = ((self.position / self.sequence.frame_seconds()) as usize).min(self.sequence.frames - 1);
+        let channels = self.sequence.frame(frame);
+        let first_channel = self.params.sequence.start_channel.max(1) - 1;
+        for (led, place) in self.leds.iter_mut().zip(self.chain.iter()) {
+            *led = match place {
+                Some(place) if first_channel + place * 3 + 3 <= channels.len() => {
+                    let channel = first_channel + place * 3;
+                    FloatColor::from(Color{r: channels[channel], g: channels[channel + 1], b: channels[channel + 2]})
+                },
+                _ => FloatColor::black(),
+            };
+        }
+        if self.playing {
+            // Loop back to the start at the end of the sequence.
+            self.position = (self.position + time.delta) % self.sequence.duration();
+        }
+    }
+    fn leds(&self) -> &[FloatColor] { &self.leds }
+    fn set_params(&mut self, params: PainterParams) {
+        if params.sequence != self.params.sequence {
+            match load_sequence(&self.sequences, &params) {
+                Ok(sequence) => self.sequence = sequence,
+                Err(e) => {
+                    println!("Unable to load sequence: {}", e);
+                    self.params = PainterParams { sequence: self.params.sequence.clone(), ..params };
+                    return;
+                },
+            }
+            self.position = 0.0;
+        }
+        self.params = params;
     }
-    if params.painter == "fade" {
-        return Box::new(FadePainter::new(bounds, params));
+    fn control(&mut self, command: SequenceCommand) {
+        match command {
+            SequenceCommand::Play => self.playing = true,
+            SequenceCommand::Pause => self.playing = false,
+            SequenceCommand::Seek(seconds) => self.position = seconds.max(0.0).min(self.sequence.duration()),
+        }
+    }
+}
+
+pub struct PainterEntry {
+    pub info: PainterInfo,
+    factory: fn(&Area, PainterParams) -> Result<Box<dyn Painter>, Box<dyn Error>>,
+}
+
+// Every painter that can be selected by PainterParams::painter. Add new painters here.
+pub static PAINTERS: &[PainterEntry] = &[
+    PainterEntry {
+        info: PainterInfo { id: "sweep", name: "Sweep",
+                            description: "A bar of the primary color sweeping along each strip." },
+        factory: |area, params| Ok(Box::new(SweepPainter::new(area.bounds.width, area.bounds.height, params))),
+    },
+    PainterEntry {
+        info: PainterInfo { id: "hex", name: "Hex",
+                            description: "Hexagons branching out across the panel." },
+        factory: |area, params| Ok(Box::new(HexPainter::new(area.bounds, params))),
+    },
+    PainterEntry {
+        info: PainterInfo { id: "line", name: "Line",
+                            description: "Two trails that cross over and bounce off the edges." },
+        factory: |area, params| Ok(Box::new(LinePainter::new(area.bounds.width, area.bounds.height, params))),
+    },
+    PainterEntry {
+        info: PainterInfo { id: "fade", name: "Fade",
+                            description: "Bands of the secondary colors scrolling along the panel." },
+        factory: |area, params| Ok(Box::new(FadePainter::new(area.bounds, params))),
+    },
+    PainterEntry {
+        info: PainterInfo { id: "rain", name: "Rain",
+                            description: "Raindrops falling down random strips." },
+        factory: |area, params| Ok(Box::new(Raindrops::new(area.bounds, params))),
+    },
+    PainterEntry {
+        info: PainterInfo { id: "disco", name: "Disco",
+                            description: "Alternating LEDs strobing to the music, or to a beat set by speed." },
+        factory: |area, params| Ok(Box::new(Disco::new(area.bounds, params))),
+    },
+    PainterEntry {
+        info: PainterInfo { id: "wave", name: "Wave",
+                            description: "Bands of the secondary colors flowing down the whole garment." },
+        factory: |area, params| Ok(Box::new(FieldPainter::new(Wave, area, params))),
+    },
+    PainterEntry {
+        info: PainterInfo { id: "ripple", name: "Ripple",
+                            description: "Rings of the secondary colors spreading out from the back." },
+        factory: |area, params| Ok(Box::new(FieldPainter::new(Ripple, area, params))),
+    },
+    PainterEntry {
+        info: PainterInfo { id: "fseq", name: "Sequence",
+                            description: "Plays the xLights sequence set in sequence.file." },
+        factory: |area, params| Ok(Box::new(FseqPainter::new(area, params)?)),
+    },
+];
+
+pub fn painter_infos() -> Vec<PainterInfo> {
+    PAINTERS.iter().map(|entry| entry.info).collect()
+}
+
+fn make_single_painter(area: &Area, params: PainterParams) -> Result<Box<dyn Painter>, Box<dyn Error>> {
+    match PAINTERS.iter().find(|entry| entry.info.id == params.painter) {
+        Some(entry) => (entry.factory)(area, params),
+        None => Err(format!("Unknown painter: {}", params.painter).into()),
     }
-    if params.painter == "rain" {
-        return Box::new(Raindrops::new(bounds, params));
+}
+
+pub fn make_painter(area: &Area, params: PainterParams) -> Result<Box<dyn Painter>, Box<dyn Error>> {
+    let base = make_single_painter(area, params.base_params())?;
+    if params.layers.len() == 0 {
+        return Ok(base);
     }
-    if params.painter == "disco" {
-        return Box::new(Disco::new(bounds, params));
+    let mut layers = Vec::with_capacity(params.layers.len());
+    for layer in params.layers.iter() {
+        layers.push((make_single_painter(area, params.layer_params(layer))?, layer.clone()));
     }
-    return Box::new(SweepPainter::new(bounds.width, bounds.height, params));
+    Ok(Box::new(LayeredPainter::new(base, layers)))
 }
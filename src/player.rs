@@ -0,0 +1,103 @@
+extern crate rand;
+use rand::prelude::*;
+use std::time::Instant;
+
+use base::{PainterParams, Playlist, PlaylistCommand};
+
+// Steps through a playlist of presets. Call tick() every frame; it never blocks.
+pub struct Player {
+    playlist: Playlist,
+    order: Vec<usize>,
+    position: usize,
+    started: Option<Instant>,  // When the current step started, or None if stopped.
+    rng: ThreadRng,
+}
+
+impl Player {
+    pub fn new(playlist: Playlist) -> Self {
+        let mut player = Player { playlist: playlist, order: Vec::new(), position: 0,
+                                  started: None, rng: rand::thread_rng() };
+        player.reorder();
+        player
+    }
+
+    fn reorder(&mut self) {
+        self.order = (0..self.playlist.entries.len()).collect();
+        if self.playlist.shuffle {
+            self.order.shuffle(&mut self.rng);
+        }
+        self.position = 0;
+    }
+
+    pub fn running(&self) -> bool { self.started.is_some() }
+
+    pub fn set_playlist(&mut self, playlist: Playlist) {
+        self.playlist = playlist;
+        self.reorder();
+        if self.running() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    pub fn command(&mut self, command: PlaylistCommand) {
+        if self.order.len() == 0 {
+            self.started = None;
+            return;
+        }
+        match command {
+            PlaylistCommand::Start => {},
+            PlaylistCommand::Stop => {
+                self.started = None;
+                return;
+            },
+            PlaylistCommand::Next => self.advance(),
+            PlaylistCommand::Previous => {
+                self.position = (self.position + self.order.len() - 1) % self.order.len();
+            },
+        }
+        self.started = Some(Instant::now());
+    }
+
+    fn advance(&mut self) {
+        self.position += 1;
+        if self.position >= self.order.len() {
+            self.reorder();
+        }
+    }
+
+    // Moves on to the next step once the current one has played for long enough. Returns
+    // true if the step changed.
+    pub fn tick(&mut self) -> bool {
+        let started = match self.started {
+            Some(started) => started,
+            None => return false,
+        };
+        if self.order.len() == 0 {
+            self.started = None;
+            return true;
+        }
+        let entry = &self.playlist.entries[self.order[self.position]];
+        let duration = entry.duration.unwrap_or(self.playlist.duration);
+        if started.elapsed().as_secs_f32() < duration {
+            return false;
+        }
+        self.advance();
+        self.started = Some(Instant::now());
+        true
+    }
+
+    // The params to paint with: the current preset applied on top of the base params.
+    pub fn params(&self, base: &PainterParams) -> PainterParams {
+        if !self.running() || self.order.len() == 0 {
+            return base.clone();
+        }
+        let entry = &self.playlist.entries[self.order[self.position]];
+        match base.update_value(&entry.preset) {
+            Ok(params) => params,
+            Err(e) => {
+                println!("Unable to apply playlist preset: {}", e);
+                base.clone()
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+/**
+ * Renders painters to image files without a display or GTK, for previews.
+ *
+ *   wavesuit render [--params FILE] [--frames N] [--size PIXELS] OUTPUT
+ *
+ * --params holds JSON fields to change, like a POST to /. OUTPUT ending in .gif is written as an
+ * animated GIF; anything else is a directory that gets one PNG per frame.
+ */
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+
+use base::{Color, Layout, PainterParams};
+
+use crate::audio::AudioFeatures;
+use crate::clock::{FrameTime, TICK_SECONDS};
+use crate::scene::{Garment, Scene};
+
+const LED_RADIUS: f32 = 0.007;  // Of the image width, matching the emulator.
+
+struct Options {
+    params: Option<String>,
+    frames: usize,
+    size: usize,
+    output: String,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, Box<dyn Error>> {
+    let mut options = Options { params: None, frames: 100, size: 500, output: String::new() };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--params" => options.params = Some(value()?.clone()),
+            "--frames" => options.frames = value()?.parse()?,
+            "--size" => options.size = value()?.parse()?,
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg).into()),
+            _ => options.output = arg.clone(),
+        }
+    }
+    if options.output.is_empty() {
+        return Err("Usage: wavesuit render [--params FILE] [--frames N] [--size PIXELS] OUTPUT".into());
+    }
+    Ok(options)
+}
+
+// Draws the LEDs as dots at their layout positions, like the emulator window.
+struct Canvas {
+    size: usize,
+    pixels: Vec<u8>,  // RGB.
+}
+
+impl Canvas {
+    fn new(size: usize) -> Self {
+        Canvas { size: size, pixels: vec![0; size * size * 3] }
+    }
+
+    fn clear(&mut self) {
+        for value in self.pixels.iter_mut() {
+            *value = 0;
+        }
+    }
+
+    fn dot(&mut self, (x, y): (f32, f32), color: Color) {
+        let size = self.size as f32;
+        let (cx, cy, radius) = (x * size, y * size, LED_RADIUS * size);
+        let top = (cy - radius).floor().max(0.0) as usize;
+        let left = (cx - radius).floor().max(0.0) as usize;
+        let bottom = ((cy + radius).ceil().max(0.0) as usize).min(self.size);
+        let right = ((cx + radius).ceil().max(0.0) as usize).min(self.size);
+        for row in top..bottom {
+            for column in left..right {
+                let (dx, dy) = (column as f32 + 0.5 - cx, row as f32 + 0.5 - cy);
+                if dx * dx + dy * dy <= radius * radius {
+                    let at = (row * self.size + column) * 3;
+                    self.pixels[at..at + 3].copy_from_slice(&[color.r, color.g, color.b]);
+                }
+            }
+        }
+    }
+}
+
+trait FrameWriter {
+    fn write(&mut self, canvas: &Canvas) -> Result<(), Box<dyn Error>>;
+}
+
+struct GifWriter {
+    encoder: gif::Encoder<BufWriter<File>>,
+}
+
+impl GifWriter {
+    fn new(path: &str, size: usize) -> Result<Self, Box<dyn Error>> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = gif::Encoder::new(file, size as u16, size as u16, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        Ok(GifWriter { encoder: encoder })
+    }
+}
+
+impl FrameWriter for GifWriter {
+    fn write(&mut self, canvas: &Canvas) -> Result<(), Box<dyn Error>> {
+        let size = canvas.size as u16;
+        let mut frame = gif::Frame::from_rgb_speed(size, size, &canvas.pixels, 10);
+        frame.delay = (TICK_SECONDS * 100.0).round() as u16;  // Hundredths of a second.
+        self.encoder.write_frame(&frame)?;
+        Ok(())
+    }
+}
+
+struct PngWriter {
+    directory: String,
+    frame: usize,
+}
+
+impl FrameWriter for PngWriter {
+    fn write(&mut self, canvas: &Canvas) -> Result<(), Box<dyn Error>> {
+        let path = format!("{}/frame_{:05}.png", self.directory, self.frame);
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?),
+                                            canvas.size as u32, canvas.size as u32);
+        encoder.set_color(png::ColorType::RGB);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&canvas.pixels)?;
+        self.frame += 1;
+        Ok(())
+    }
+}
+
+pub fn run(args: &[String], layout: &Layout, mut params: PainterParams) -> Result<(), Box<dyn Error>> {
+    let options = parse_args(args)?;
+    if let Some(path) = options.params.as_ref() {
+        params = params.update(&fs::read_to_string(path)?)?;
+    }
+
+    let mut writer: Box<dyn FrameWriter> = if options.output.ends_with(".gif") {
+        Box::new(GifWriter::new(&options.output, options.size)?)
+    } else {
+        fs::create_dir_all(&options.output)?;
+        Box::new(PngWriter { directory: options.output.clone(), frame: 0 })
+    };
+
+    // Dimmed as the LEDs would be.
+    let brightness = params.global_brightness;
+    let mut scene = Scene::new(Garment::new(layout, params.belt_only), params)?;
+    let mut canvas = Canvas::new(options.size);
+
+    for frame in 0..options.frames {
+        // Step time at the reference tick rate, so renders are the same on any machine.
+        let time = FrameTime { seconds: frame as f64 * TICK_SECONDS as f64, delta: TICK_SECONDS,
+                               audio: AudioFeatures::default() };
+        scene.paint(&time);
+        canvas.clear();
+        for (&position, &color) in scene.garment.coords.iter().zip(scene.frame().iter()) {
+            canvas.dot(position, (color * brightness).round());
+        }
+        writer.write(&canvas)?;
+    }
+    println!("Rendered {} frames to {}", options.frames, options.output);
+    Ok(())
+}
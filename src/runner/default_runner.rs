@@ -5,8 +5,6 @@ use std::error::Error;
 use crossbeam_channel::{bounded, tick, Receiver, select};
 use signal_hook::{iterator::Signals, SIGINT, SIGTERM};
 
-use crate::display;
-
 // Set up signal handlers to listen on their own thread.
 fn ctrl_channel() -> Result<Receiver<()>, Box<dyn Error>> {
     let signals = Signals::new(&[SIGINT, SIGTERM])?;
@@ -22,10 +20,6 @@ fn ctrl_channel() -> Result<Receiver<()>, Box<dyn Error>> {
     Ok(receiver)
 }
 
-pub fn get_display(dots: usize) -> Result<Box<dyn display::Display>, Box<dyn Error>> {
-    display::new(dots)
-}
-
 pub fn run<F>(mut core_alg: F) -> Result<(), Box<dyn Error>>
 where F: FnMut() + 'static {
     let ticks = tick(Duration::from_millis(30));
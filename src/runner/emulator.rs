@@ -4,6 +4,7 @@ extern crate gtk;
 
 use std::error::Error;
 use std::f64::consts::PI;
+use std::sync::Mutex;
 
 use gio::prelude::*;
 use gtk::prelude::*;
@@ -12,83 +13,32 @@ use gtk::DrawingArea;
 use cairo::Context;
 
 use crate::display::Display;
-use base::Color;
+use base::{Color, Layout};
 
-static mut LEDS: Vec<Color> = Vec::new();
+// Set from the output thread and drawn from the GTK one.
+static LEDS: Mutex<Vec<Color>> = Mutex::new(Vec::new());
 
-struct EmulatorDisplay {
-    offset: usize,
-}
+struct EmulatorDisplay;
 
 impl Display for EmulatorDisplay {
     fn set_pixel(&mut self, index: usize, r: u8, g: u8, b: u8) {
-        unsafe {
-            LEDS[index + self.offset] = Color{r: r, g: g, b: b};
+        // Routes can point past the layout, so ignore LEDs it doesn't have.
+        if let Some(led) = LEDS.lock().unwrap().get_mut(index) {
+            *led = Color{r: r, g: g, b: b};
         }
     }
 
     fn show(&mut self) -> Result<(), Box<dyn Error>> {
         Ok(())
     }
-
-    fn set_offset(&mut self, offset: usize) {
-        self.offset = offset;
-    }
-}
-
-struct LedStrip {
-    count: usize,
-    x_start: f64,
-    y_start: f64,
-    x_spacing: f64,
-    y_spacing: f64,
-    backwards: bool,
 }
 
+// Positions of each display LED, scaled to the window.
 struct LedLayout {
-    strips: Vec<LedStrip>,
-    count: usize,
+    leds: Vec<(f64, f64)>,
 }
 
-impl LedLayout {
-    fn add_strip(&mut self, strip: LedStrip) {
-        self.count += strip.count;
-        if !strip.backwards {
-            self.strips.push(strip);
-            return;
-        }
-        let backwards_strip = LedStrip{count: strip.count,
-                                       x_start: strip.x_start + strip.x_spacing * (strip.count as f64 - 1.0),
-                                       y_start: strip.y_start + strip.y_spacing * (strip.count as f64 - 1.0),
-                                       x_spacing: -strip.x_spacing,
-                                       y_spacing: -strip.y_spacing,
-                                       backwards: false};
-        self.strips.push(backwards_strip);
-    }
-    fn add_offset_panel(&mut self, mut orig_x: f64, mut orig_y: f64,
-                        strip_x: f64, strip_y: f64, strip_len: usize,
-                        panel_x: f64, panel_y: f64, panel_len: usize,
-                        mut backwards: bool) {
-        for _i in 0..panel_len {
-            self.add_strip(LedStrip{count: strip_len, x_start: orig_x, y_start: orig_y,
-                                    x_spacing: strip_x, y_spacing: strip_y, backwards: backwards});
-            orig_x += panel_x;
-            orig_y += panel_y;
-
-            // Add offset:
-            backwards = !backwards;
-            if backwards {
-                orig_x += strip_x / 2.0;
-                orig_y += strip_y / 2.0;
-            } else {
-                orig_x -= strip_x / 2.0;
-                orig_y -= strip_y / 2.0;
-            }
-        }
-    }
-}
-
-static mut LAYOUT: LedLayout = LedLayout{strips: Vec::new(), count: 0};
+static LAYOUT: Mutex<LedLayout> = Mutex::new(LedLayout{leds: Vec::new()});
 
 // Based on https://github.com/gtk-rs/examples/blob/master/src/bin/cairotest.rs
 fn build_ui(application: &gtk::Application)
@@ -99,23 +49,14 @@ fn build_ui(application: &gtk::Application)
         cr.rectangle(0.0, 0.0, 1.0, 1.0);
         cr.fill();
 
-        unsafe {
-            let mut led_index: usize = 0;
-            for strip in LAYOUT.strips.iter() {
-                let mut x = strip.x_start;
-                let mut y = strip.y_start;
-                for _strip_index in 0..strip.count {
-                    let color = if LEDS.len() > led_index { LEDS[led_index] } else { Color::black() };
-                    cr.set_source_rgb(color.r as f64 / 255.0,
-                                       color.g as f64 / 255.0,
-                                       color.b as f64 / 255.0);
-                    cr.arc(x, y, 0.007, 0.0, PI * 2.);
-                    cr.fill();
-                    x += strip.x_spacing;
-                    y += strip.y_spacing;
-                    led_index += 1;
-                }
-            }
+        let leds = LEDS.lock().unwrap();
+        for (led_index, &(x, y)) in LAYOUT.lock().unwrap().leds.iter().enumerate() {
+            let color = if leds.len() > led_index { leds[led_index] } else { Color::black() };
+            cr.set_source_rgb(color.r as f64 / 255.0,
+                               color.g as f64 / 255.0,
+                               color.b as f64 / 255.0);
+            cr.arc(x, y, 0.007, 0.0, PI * 2.);
+            cr.fill();
         }
         Inhibit(false)
     });
@@ -142,42 +83,14 @@ where
 
 }
 
-pub fn get_display(dots: usize) -> Result<Box<dyn Display>, Box<dyn Error>> {
-    unsafe {
-        // Back
-        let unit: f64 = 1.0 / 46.0;
-        LAYOUT.add_offset_panel(0.5 + unit * 9.0 /* orig_x */, unit * 4.0 /* orig_y */,
-                                0.0 /* strip_x */, unit /* strip_y */, 30 /* strip_len */,
-                                -unit /* panel_x */, 0.0 /* panel_y */, 8 /* panel_len */,
-                                false /* backwards */);
-
-        LAYOUT.add_offset_panel(0.5 - unit * 1.0 /* orig_x */, unit * 4.0 /* orig_y */,
-                                0.0 /* strip_x */, unit /* strip_y */, 30 /* strip_len */,
-                                -unit /* panel_x */, 0.0 /* panel_y */, 8 /* panel_len */,
-                                false /* backwards */);
-
-        // Sleeve (oops the first strip has a broken led).
-        LAYOUT.add_offset_panel(unit * 7.0 /* orig_x */, unit * 5.0 /* orig_y */,
-                                0.0 /* strip_x */, unit /* strip_y */, 29 /* strip_len */,
-                                -unit /*panel_x */, 0.0 /* panel_y */ , 1 /* panel_len */,
-                                false /* backwards */);
-        LAYOUT.add_offset_panel(unit * 6.0 /* orig_x */, unit * 4.5 /* orig_y */,
-                                0.0 /* strip_x */, unit /* strip_y */, 30 /* strip_len */,
-                                -unit /*panel_x */, 0.0 /* panel_y */ , 3 /* panel_len */,
-                                true /* backwards */);
-
-        // Belt
-        LAYOUT.add_offset_panel(0.5 - unit * 10.0 /* orig_x */, 1.0 - unit * 5.0 /* orig_y */,
-                                unit /* strip_x */, 0.0 /* strip_y */, 22 /* strip_len */,
-                                0.0 /* panel_x */, -unit /* panel_y */, 4 /* panel_len */,
-                                false /* backwards */);
-    }
+// The emulator window only draws while the emulator runner is running.
+pub fn make_display(layout: &Layout) -> Result<Box<dyn Display>, Box<dyn Error>> {
+    let leds = layout.display_positions();
 
     println!("Using an emulator display");
-    unsafe {
-        LEDS.resize_with(dots, || {Color{r: 0, g: 0, b: 0}});
-    }
-    Ok(Box::new(EmulatorDisplay{offset: 0}))
+    LEDS.lock().unwrap().resize_with(leds.len(), || {Color{r: 0, g: 0, b: 0}});
+    LAYOUT.lock().unwrap().leds = leds;
+    Ok(Box::new(EmulatorDisplay))
 }
 
 pub fn run<F>(mut core_alg: F) -> Result<(), Box<dyn Error>>
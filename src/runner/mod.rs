@@ -0,0 +1,19 @@
+use std::error::Error;
+
+use base::RunnerKind;
+
+pub mod default_runner;
+#[cfg(feature = "emulator")]
+pub mod emulator;
+
+// Calls core_alg for every frame until the program is asked to stop.
+pub fn run<F>(kind: RunnerKind, core_alg: F) -> Result<(), Box<dyn Error>>
+where F: FnMut() + 'static {
+    match kind {
+        RunnerKind::Timer => default_runner::run(core_alg),
+        #[cfg(feature = "emulator")]
+        RunnerKind::Emulator => emulator::run(core_alg),
+        #[cfg(not(feature = "emulator"))]
+        RunnerKind::Emulator => Err("wavesuit was built without the emulator feature".into()),
+    }
+}
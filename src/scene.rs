@@ -0,0 +1,105 @@
+use std::error::Error;
+
+use base::{chain_length, display_map, FloatColor, Layout, PainterParams, SequenceCommand};
+
+use crate::clock::FrameTime;
+use crate::fseq::SequenceCache;
+use crate::painter::{self, Area, Painter};
+
+// The segments lit for one garment setting, and where their LEDs are on the display.
+#[derive(Clone)]
+pub struct Garment {
+    pub belt_only: bool,
+    pub areas: Vec<Area>,
+    maps: Vec<Vec<Option<usize>>>,
+    // Normalized position of each display LED.
+    pub coords: Vec<(f32, f32)>,
+}
+
+impl Garment {
+    pub fn new(layout: &Layout, belt_only: bool) -> Self {
+        let segments = layout.active_segments(belt_only);
+        let maps = display_map(&segments);
+        let areas: Vec<Area> = segments.iter().map(|x| Area::new(layout, x)).collect();
+        let mut coords = vec![(0.0, 0.0); chain_length(&segments)];
+        for (area, map) in areas.iter().zip(maps.iter()) {
+            for (pix, led) in map.iter().enumerate() {
+                if let Some(led) = led {
+                    coords[*led] = area.coords[pix];
+                }
+            }
+        }
+        Garment { belt_only: belt_only, areas: areas, maps: maps, coords: coords }
+    }
+    pub fn size(&self) -> usize { self.coords.len() }
+}
+
+fn make_painters(areas: &[Area], params: &PainterParams) -> Result<Vec<Box<dyn Painter>>, Box<dyn Error>> {
+    areas.iter().map(|area: &Area| {
+        painter::make_painter(area, params.zone_params(&area.name))
+    }).collect()
+}
+
+// A painter for each area of a garment, painting into a frame in display order.
+pub struct Scene {
+    pub garment: Garment,
+    painters: Vec<Box<dyn Painter>>,
+    params: PainterParams,
+    frame: Vec<FloatColor>,
+}
+
+impl Scene {
+    pub fn new(mut garment: Garment, params: PainterParams) -> Result<Self, Box<dyn Error>> {
+        // Each scene loads its sequences afresh, so edits to the files show up when it changes.
+        let sequences = SequenceCache::default();
+        for area in garment.areas.iter_mut() {
+            area.sequences = sequences.clone();
+        }
+        let painters = make_painters(&garment.areas, &params)?;
+        let frame = vec![FloatColor::black(); garment.size()];
+        Ok(Scene { garment: garment, painters: painters, params: params, frame: frame })
+    }
+
+    pub fn paint(&mut self, time: &FrameTime) {
+        for (painter, map) in self.painters.iter_mut().zip(self.garment.maps.iter()) {
+            painter.paint(time);
+            for (&color, led) in painter.leds().iter().zip(map.iter()) {
+                if let Some(led) = led {
+                    self.frame[*led] = color;
+                }
+            }
+        }
+    }
+
+    pub fn frame(&self) -> &[FloatColor] { &self.frame }
+
+    // Whether new params would run different painters in any zone.
+    pub fn needs_rebuild(&self, new_params: &PainterParams) -> bool {
+        self.garment.areas.iter().any(|area| {
+            new_params.zone_params(&area.name).painter_ids() !=
+                self.params.zone_params(&area.name).painter_ids()
+        })
+    }
+
+    // Hands new params to the painters, replacing any whose painter changed.
+    pub fn set_params(&mut self, new_params: PainterParams) {
+        for (painter, area) in self.painters.iter_mut().zip(self.garment.areas.iter()) {
+            let zone_params = new_params.zone_params(&area.name);
+            if zone_params.painter_ids() == self.params.zone_params(&area.name).painter_ids() {
+                painter.set_params(zone_params);
+                continue;
+            }
+            match painter::make_painter(area, zone_params) {
+                Ok(new_painter) => *painter = new_painter,
+                Err(e) => println!("Unable to switch painters in {}: {}", area.name, e),
+            }
+        }
+        self.params = new_params;
+    }
+
+    pub fn control(&mut self, command: SequenceCommand) {
+        for painter in self.painters.iter_mut() {
+            painter.control(command);
+        }
+    }
+}
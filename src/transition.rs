@@ -0,0 +1,94 @@
+extern crate rand;
+use rand::prelude::*;
+use std::time::Instant;
+
+use base::{FloatColor, TransitionKind, TransitionParams, WipeDirection};
+
+use crate::clock::FrameTime;
+use crate::scene::Scene;
+
+// Blends from an outgoing scene to the incoming one over the transition's duration.
+pub struct Transition {
+    outgoing: Scene,
+    settings: TransitionParams,
+    started: Instant,
+    thresholds: Vec<f32>,  // When each LED switches over in a dissolve or wipe, from 0 to 1.
+    frame: Vec<FloatColor>,
+}
+
+fn mix(from: FloatColor, to: FloatColor, amount: f32) -> FloatColor {
+    from * (1.0 - amount) + to * amount
+}
+
+impl Transition {
+    pub fn new(outgoing: Scene, incoming: &Scene, mut settings: TransitionParams) -> Self {
+        // The garments don't share LEDs, so the only way to blend them is through black.
+        if outgoing.garment.belt_only != incoming.garment.belt_only {
+            settings.kind = TransitionKind::FadeThroughBlack;
+        }
+        let coords = &incoming.garment.coords;
+        let thresholds = match settings.kind {
+            TransitionKind::Dissolve => {
+                let mut rng = rand::thread_rng();
+                coords.iter().map(|_| rng.gen()).collect()
+            },
+            TransitionKind::Wipe => {
+                let along: Vec<f32> = coords.iter().map(|&(x, y)| match settings.direction {
+                    WipeDirection::Down => y,
+                    WipeDirection::Up => -y,
+                    WipeDirection::Right => x,
+                    WipeDirection::Left => -x,
+                }).collect();
+                let start = along.iter().cloned().fold(std::f32::INFINITY, f32::min);
+                let end = along.iter().cloned().fold(std::f32::NEG_INFINITY, f32::max);
+                along.iter().map(|position| (position - start) / (end - start).max(0.001)).collect()
+            },
+            _ => Vec::new(),
+        };
+        Transition { outgoing: outgoing, settings: settings, started: Instant::now(),
+                     thresholds: thresholds, frame: Vec::new() }
+    }
+
+    fn progress(&self) -> f32 {
+        if self.settings.duration <= 0.0 {
+            return 1.0;
+        }
+        (self.started.elapsed().as_secs_f32() / self.settings.duration).min(1.0)
+    }
+
+    pub fn finished(&self) -> bool { self.progress() >= 1.0 }
+
+    // Paints the outgoing scene and mixes it with the already painted incoming scene. Returns
+    // the frame to show and whether it is for the belt.
+    pub fn render(&mut self, incoming: &Scene, time: &FrameTime) -> (&[FloatColor], bool) {
+        let progress = self.progress();
+        self.outgoing.paint(time);
+        if self.settings.kind == TransitionKind::FadeThroughBlack {
+            let (scene, brightness) = if progress < 0.5 {
+                (&self.outgoing, 1.0 - progress * 2.0)
+            } else {
+                (incoming, progress * 2.0 - 1.0)
+            };
+            self.frame.clear();
+            self.frame.extend(scene.frame().iter().map(|&color| color * brightness));
+            return (&self.frame, scene.garment.belt_only);
+        }
+
+        // Wipes have a soft edge this wide, as a fraction of the garment.
+        let edge: f32 = 0.1;
+        self.frame.clear();
+        for (led, (&from, &to)) in self.outgoing.frame().iter().zip(incoming.frame().iter()).enumerate() {
+            let amount = match self.settings.kind {
+                TransitionKind::Crossfade => progress,
+                TransitionKind::Dissolve => if progress > self.thresholds[led] { 1.0 } else { 0.0 },
+                TransitionKind::Wipe => {
+                    let front = progress * (1.0 + edge);
+                    ((front - self.thresholds[led]) / edge).max(0.0).min(1.0)
+                },
+                _ => 1.0,
+            };
+            self.frame.push(mix(from, to, amount));
+        }
+        (&self.frame, incoming.garment.belt_only)
+    }
+}